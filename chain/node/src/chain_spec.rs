@@ -207,6 +207,8 @@ fn testnet_genesis(
 			orgs,
 			members,
 		}),
+		product_registry: Some(Default::default()),
+		product_tracking: Some(Default::default()),
 		pallet_collective_Instance1: Some(Default::default()),
 		pallet_elections_phragmen: Some(Default::default()),
 		pallet_democracy: Some(Default::default()),
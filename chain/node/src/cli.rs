@@ -8,6 +8,17 @@ pub struct Cli {
 
     #[structopt(flatten)]
     pub run: RunCmd,
+
+    /// URL of a listener notified of the shipping events by the off-chain worker,
+    /// followed by `,disabled` to configure the listener without notifying it.
+    /// Can be repeated to notify several listeners.
+    #[structopt(long = "tracking-listener-url", value_name = "URL")]
+    pub tracking_listener_urls: Vec<String>,
+
+    /// Block from which the off-chain worker sends the shipping events notifications,
+    /// instead of resuming after the last processed block.
+    #[structopt(long = "tracking-start-block", value_name = "BLOCK")]
+    pub tracking_start_block: Option<u32>,
 }
 
 #[derive(Debug, StructOpt)]
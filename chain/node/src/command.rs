@@ -149,9 +149,11 @@ pub fn run() -> sc_cli::Result<()> {
         }
         None => {
             let runner = cli.create_runner(&cli.run)?;
+            let listener_urls = cli.tracking_listener_urls.clone();
+            let start_block = cli.tracking_start_block;
             runner.run_node_until_exit(|config| match config.role {
                 Role::Light => service::new_light(config),
-                _ => service::new_full(config),
+                _ => service::new_full(config, listener_urls, start_block),
             })
         }
     }
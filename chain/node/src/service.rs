@@ -2,7 +2,8 @@
 
 use std::sync::Arc;
 use std::time::Duration;
-use sc_client_api::{ExecutorProvider, RemoteBackend};
+use codec::Encode;
+use sc_client_api::{Backend, ExecutorProvider, RemoteBackend};
 use enterprise_sample_runtime::{self, opaque::Block, RuntimeApi};
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sp_inherents::InherentDataProviders;
@@ -10,6 +11,8 @@ use sc_executor::native_executor_instance;
 pub use sc_executor::NativeExecutor;
 use sp_consensus_aura::sr25519::{AuthorityPair as AuraPair};
 use sc_finality_grandpa::{FinalityProofProvider as GrandpaFinalityProofProvider, SharedVoterState};
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+use pallet_product_tracking::{ListenerEndpoint, LISTENER_ENDPOINTS_KEY, START_BLOCK_KEY};
 
 // Our native executor instance.
 native_executor_instance!(
@@ -78,8 +81,34 @@ pub fn new_partial(config: &Configuration) -> Result<sc_service::PartialComponen
 	})
 }
 
+/// Stores the listener endpoints of the product tracking off-chain worker
+/// in the offchain local storage, replacing the previous ones.
+fn set_listener_endpoints(backend: &FullBackend, urls: Vec<String>) {
+	if urls.is_empty() {
+		return;
+	}
+	if let Some(mut storage) = backend.offchain_storage() {
+		let endpoints = urls.into_iter()
+			.map(|url| ListenerEndpoint::parse(&url))
+			.collect::<Vec<_>>();
+		storage.set(STORAGE_PREFIX, LISTENER_ENDPOINTS_KEY, &endpoints.encode());
+	}
+}
+
+/// Stores the block from which the product tracking off-chain worker
+/// sends notifications in the offchain local storage.
+fn set_start_block(backend: &FullBackend, start_block: Option<u32>) {
+	if let (Some(start_block), Some(mut storage)) = (start_block, backend.offchain_storage()) {
+		storage.set(STORAGE_PREFIX, START_BLOCK_KEY, &start_block.encode());
+	}
+}
+
 /// Builds a new service for a full client.
-pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	config: Configuration,
+	listener_urls: Vec<String>,
+	start_block: Option<u32>,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client, backend, mut task_manager, import_queue, keystore, select_chain, transaction_pool,
 		inherent_data_providers,
@@ -103,6 +132,8 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 		})?;
 
 	if config.offchain_worker.enabled {
+		set_listener_endpoints(&backend, listener_urls);
+		set_start_block(&backend, start_block);
 		sc_service::build_offchain_workers(
 			&config, backend.clone(), task_manager.spawn_handle(), client.clone(), network.clone(),
 		);
@@ -118,11 +149,13 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let offchain_storage = backend.offchain_storage();
 
 		Box::new(move |deny_unsafe, _| {
 			let deps = crate::rpc::FullDeps {
 				client: client.clone(),
 				pool: pool.clone(),
+				offchain_storage: offchain_storage.clone(),
 				deny_unsafe,
 			};
 
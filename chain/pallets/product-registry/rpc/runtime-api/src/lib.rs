@@ -0,0 +1,33 @@
+//! Runtime API definition for the Product Registry pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use product_registry::{Certificate, Product, ProductId};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+// A page of products of an organization
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ProductsPage<AccountId, Moment> {
+    pub products: Vec<Product<AccountId, Moment>>,
+    // Cursor to pass to get the next page, if any
+    pub next_cursor: Option<ProductId>,
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait ProductRegistryApi<AccountId, Moment> where
+        AccountId: Codec,
+        Moment: Codec,
+    {
+        fn product_by_id(id: ProductId) -> Option<Product<AccountId, Moment>>;
+        fn products_of_organization(
+            org: AccountId,
+            cursor: Option<ProductId>,
+            limit: u32,
+        ) -> ProductsPage<AccountId, Moment>;
+        fn valid_certificates(product_id: ProductId) -> Vec<Certificate<AccountId, Moment>>;
+        fn components_tree(product_id: ProductId, max_depth: u32) -> Vec<ProductId>;
+        fn products_containing(product_id: ProductId, max_depth: u32) -> Vec<ProductId>;
+    }
+}
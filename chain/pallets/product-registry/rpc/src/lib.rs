@@ -0,0 +1,339 @@
+//! RPC interface for the Product Registry pallet.
+//! Exposes the `productRegistry_*` methods, so that clients can query products
+//! without reading (and decoding) raw storage.
+
+use std::sync::Arc;
+
+use codec::{Codec, Encode};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use product_registry::{Certificate, Product, ProductStatus, PropValue};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, H256};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_product_registry_rpc_runtime_api::ProductRegistryApi as ProductRegistryRuntimeApi;
+
+// Number of products returned per page when no limit is given
+const DEFAULT_PAGE_SIZE: u32 = 20;
+// Maximum number of products returned per page
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// A product property, with its name & value either as raw bytes (the SCALE-encoded typed value)
+/// or decoded as text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct PropertyInfo<Data> {
+	pub name: Data,
+	pub value: Data,
+}
+
+/// A product, with its ID & properties either as raw bytes or decoded as text.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProductInfo<AccountId, Moment, Data> {
+	pub id: Data,
+	pub owner: AccountId,
+	pub props: Option<Vec<PropertyInfo<Data>>>,
+	pub registered: Moment,
+	pub status: String,
+}
+
+/// A page of products of an organization.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ProductsPage<AccountId, Moment> {
+	pub products: Vec<ProductInfo<AccountId, Moment, Bytes>>,
+	/// Cursor to pass to get the next page, if any.
+	pub next_cursor: Option<Bytes>,
+}
+
+/// A product certificate.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CertificateInfo<AccountId, Moment> {
+	pub id: String,
+	pub product_id: Bytes,
+	pub certifier: AccountId,
+	pub scheme: String,
+	pub valid_from: Moment,
+	pub valid_until: Moment,
+	pub doc_hash: H256,
+	pub issued: Moment,
+}
+
+/// Product Registry RPC methods.
+#[rpc]
+pub trait ProductRegistryApi<BlockHash, AccountId, Moment> {
+	/// Returns the product with the given ID.
+	#[rpc(name = "productRegistry_getProduct")]
+	fn get_product(
+		&self,
+		id: Bytes,
+		at: Option<BlockHash>,
+	) -> Result<Option<ProductInfo<AccountId, Moment, Bytes>>>;
+
+	/// Returns the product with the given ID, with its ID & properties decoded as text.
+	#[rpc(name = "productRegistry_getDecodedProduct")]
+	fn get_decoded_product(
+		&self,
+		id: Bytes,
+		at: Option<BlockHash>,
+	) -> Result<Option<ProductInfo<AccountId, Moment, String>>>;
+
+	/// Returns a page of the products of an organization, starting after `cursor`.
+	#[rpc(name = "productRegistry_getProductsOfOrganization")]
+	fn get_products_of_organization(
+		&self,
+		org: AccountId,
+		cursor: Option<Bytes>,
+		limit: Option<u32>,
+		at: Option<BlockHash>,
+	) -> Result<ProductsPage<AccountId, Moment>>;
+
+	/// Returns the currently valid (i.e. neither expired nor revoked) certificates of a product.
+	#[rpc(name = "productRegistry_getValidCertificates")]
+	fn get_valid_certificates(
+		&self,
+		product_id: Bytes,
+		at: Option<BlockHash>,
+	) -> Result<Vec<CertificateInfo<AccountId, Moment>>>;
+
+	/// Returns the products (in)directly used as components of a product, down to `max_depth`
+	/// (the runtime's `BomMaxDepth` by default, and at most).
+	#[rpc(name = "productRegistry_getComponentsTree")]
+	fn get_components_tree(
+		&self,
+		product_id: Bytes,
+		max_depth: Option<u32>,
+		at: Option<BlockHash>,
+	) -> Result<Vec<Bytes>>;
+
+	/// Returns the products (in)directly containing a product as a component, up to `max_depth`
+	/// (the runtime's `BomMaxDepth` by default, and at most).
+	#[rpc(name = "productRegistry_getProductsContaining")]
+	fn get_products_containing(
+		&self,
+		product_id: Bytes,
+		max_depth: Option<u32>,
+		at: Option<BlockHash>,
+	) -> Result<Vec<Bytes>>;
+}
+
+/// Implements the Product Registry RPC methods, by calling into the runtime API.
+pub struct ProductRegistry<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> ProductRegistry<C, B> {
+	/// Create new `ProductRegistry` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query products.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+fn status_name(status: &ProductStatus) -> String {
+	match status {
+		ProductStatus::Active => "Active",
+		ProductStatus::Discontinued => "Discontinued",
+		ProductStatus::Recalled => "Recalled",
+	}
+	.into()
+}
+
+fn product_info<AccountId, Moment, Data>(
+	product: Product<AccountId, Moment>,
+	data: impl Fn(&[u8]) -> Data,
+	value: impl Fn(&PropValue) -> Data,
+) -> ProductInfo<AccountId, Moment, Data>
+where
+	AccountId: Clone,
+	Moment: Clone,
+{
+	ProductInfo {
+		id: data(product.id()),
+		owner: product.owner().clone(),
+		props: product.props().as_ref().map(|props| {
+			props
+				.iter()
+				.map(|prop| PropertyInfo {
+					name: data(prop.name()),
+					value: value(prop.value()),
+				})
+				.collect()
+		}),
+		registered: product.registered().clone(),
+		status: status_name(product.status()),
+	}
+}
+
+fn certificate_info<AccountId, Moment>(
+	certificate: Certificate<AccountId, Moment>,
+) -> CertificateInfo<AccountId, Moment>
+where
+	AccountId: Clone,
+	Moment: Clone,
+{
+	CertificateInfo {
+		id: decoded(certificate.id()),
+		product_id: raw(certificate.product_id()),
+		certifier: certificate.certifier().clone(),
+		scheme: decoded(certificate.scheme()),
+		valid_from: certificate.valid_from().clone(),
+		valid_until: certificate.valid_until().clone(),
+		doc_hash: H256::from(*certificate.doc_hash()),
+		issued: certificate.issued().clone(),
+	}
+}
+
+fn raw(data: &[u8]) -> Bytes {
+	data.to_vec().into()
+}
+
+fn raw_value(value: &PropValue) -> Bytes {
+	value.encode().into()
+}
+
+fn decoded(data: &[u8]) -> String {
+	String::from_utf8_lossy(data).into_owned()
+}
+
+fn decoded_value(value: &PropValue) -> String {
+	match value {
+		PropValue::Text(text) => decoded(text),
+		PropValue::Integer(integer) => integer.to_string(),
+		PropValue::Decimal(decimal) => decimal.to_string(),
+		PropValue::Boolean(boolean) => boolean.to_string(),
+		PropValue::Timestamp(timestamp) => timestamp.to_string(),
+		PropValue::Hash(hash) => format!("{:?}", H256::from(*hash)),
+		PropValue::UnitQuantity(quantity) => format!("{} {}", quantity.value, decoded(&quantity.unit)),
+	}
+}
+
+impl<C, Block, AccountId, Moment> ProductRegistryApi<<Block as BlockT>::Hash, AccountId, Moment>
+	for ProductRegistry<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: ProductRegistryRuntimeApi<Block, AccountId, Moment>,
+	AccountId: Codec + Clone,
+	Moment: Codec + Clone,
+{
+	fn get_product(
+		&self,
+		id: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<ProductInfo<AccountId, Moment, Bytes>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let product = api.product_by_id(&at, id.to_vec()).map_err(runtime_error)?;
+		Ok(product.map(|product| product_info(product, raw, raw_value)))
+	}
+
+	fn get_decoded_product(
+		&self,
+		id: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<ProductInfo<AccountId, Moment, String>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let product = api.product_by_id(&at, id.to_vec()).map_err(runtime_error)?;
+		Ok(product.map(|product| product_info(product, decoded, decoded_value)))
+	}
+
+	fn get_products_of_organization(
+		&self,
+		org: AccountId,
+		cursor: Option<Bytes>,
+		limit: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<ProductsPage<AccountId, Moment>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+		let page = api
+			.products_of_organization(&at, org, cursor.map(|c| c.to_vec()), limit)
+			.map_err(runtime_error)?;
+		Ok(ProductsPage {
+			products: page
+				.products
+				.into_iter()
+				.map(|product| product_info(product, raw, raw_value))
+				.collect(),
+			next_cursor: page.next_cursor.map(Into::into),
+		})
+	}
+
+	fn get_valid_certificates(
+		&self,
+		product_id: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<CertificateInfo<AccountId, Moment>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let certificates = api
+			.valid_certificates(&at, product_id.to_vec())
+			.map_err(runtime_error)?;
+		Ok(certificates.into_iter().map(certificate_info).collect())
+	}
+
+	fn get_components_tree(
+		&self,
+		product_id: Bytes,
+		max_depth: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<Bytes>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let max_depth = max_depth.unwrap_or(u32::max_value());
+
+		let products = api
+			.components_tree(&at, product_id.to_vec(), max_depth)
+			.map_err(runtime_error)?;
+		Ok(products.into_iter().map(Into::into).collect())
+	}
+
+	fn get_products_containing(
+		&self,
+		product_id: Bytes,
+		max_depth: Option<u32>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<Bytes>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		let max_depth = max_depth.unwrap_or(u32::max_value());
+
+		let products = api
+			.products_containing(&at, product_id.to_vec(), max_depth)
+			.map_err(runtime_error)?;
+		Ok(products.into_iter().map(Into::into).collect())
+	}
+}
@@ -0,0 +1,50 @@
+// GS1 GTIN (Global Trade Item Number) helpers
+// See https://www.gs1.org/standards/id-keys/gtin
+
+use frame_support::sp_std::prelude::*;
+
+// Valid lengths for GTIN-8, GTIN-12, GTIN-13 & GTIN-14
+pub const GTIN_LENGTHS: [usize; 4] = [8, 12, 13, 14];
+
+// Name of the DID attribute holding the GS1 Company Prefix of an organization
+pub const COMPANY_PREFIX_ATTRIBUTE: &[u8] = b"GS1CompanyPrefix";
+
+pub fn is_valid_format(gtin: &[u8]) -> bool {
+    GTIN_LENGTHS.contains(&gtin.len()) && gtin.iter().all(|c| c.is_ascii_digit())
+}
+
+// Computes the check digit (last digit) of a GTIN, the input must be a valid GTIN format.
+pub fn check_digit(gtin: &[u8]) -> u8 {
+    // Digits are weighted 3, 1, 3, 1, ... starting from the right (check digit excluded)
+    let sum: u32 = gtin[..gtin.len() - 1]
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, c)| u32::from(c - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+pub fn is_valid_check_digit(gtin: &[u8]) -> bool {
+    check_digit(gtin) == gtin[gtin.len() - 1] - b'0'
+}
+
+// GTIN-8 are allocated individually from a separate number range,
+// they do not contain a GS1 Company Prefix unlike other GTINs.
+pub fn has_company_prefix(gtin: &[u8]) -> bool {
+    gtin.len() != 8
+}
+
+// Returns the GTIN as 13 digits, the form in which the GS1 Company Prefix appears
+// (GTIN-14 are stripped of their indicator digit, GTIN-12 are zero-padded).
+// Only relevant for GTINs having a company prefix.
+pub fn to_gtin13(gtin: &[u8]) -> Vec<u8> {
+    match gtin.len() {
+        14 => gtin[1..].to_vec(),
+        len => {
+            let mut gtin13 = vec![b'0'; 13 - len];
+            gtin13.extend_from_slice(gtin);
+            gtin13
+        }
+    }
+}
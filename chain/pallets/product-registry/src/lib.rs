@@ -4,9 +4,13 @@
 
 use codec::{Decode, Encode};
 use core::result::Result;
+use fixed::types::I32F32;
 use frame_support::{
     decl_error, decl_event, decl_module, decl_storage, dispatch, ensure, sp_runtime::RuntimeDebug,
-    sp_std::prelude::*, traits::EnsureOrigin,
+    sp_std::{collections::btree_set::BTreeSet, prelude::*},
+    storage::IterableStorageDoubleMap,
+    traits::{EnsureOrigin, Get},
+    weights::Weight,
 };
 use frame_system::{self as system, ensure_signed};
 
@@ -16,22 +20,28 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
-// General constraints to limit data size
-// Note: these could also be passed as trait config parameters
-pub const PRODUCT_ID_MAX_LENGTH: usize = 36;
-pub const PRODUCT_PROP_NAME_MAX_LENGTH: usize = 10;
-pub const PRODUCT_PROP_VALUE_MAX_LENGTH: usize = 20;
-pub const PRODUCT_MAX_PROPS: usize = 3;
+mod migration;
+
+pub mod gs1;
 
 // Custom types
 pub type ProductId = Vec<u8>;
 pub type PropName = Vec<u8>;
-pub type PropValue = Vec<u8>;
+pub type PropDecimal = I32F32;
+// Unit code, as defined by the Unified Code for Units of Measure (UCUM) e.g. kg, mL, Cel
+pub type UnitCode = Vec<u8>;
+pub type LotId = Vec<u8>;
+pub type SchemaId = Vec<u8>;
+pub type CertificateId = Vec<u8>;
+// Product to register in a batch: product ID, owner, props & (optional) schema ID
+pub type ProductRegistration<AccountId> = (ProductId, AccountId, Option<Vec<ProductProperty>>, Option<SchemaId>);
+// Certification scheme e.g. organic, halal, ISO 22000, GMP
+pub type CertificationScheme = Vec<u8>;
 
 // Product contains master data (aka class-level) about a trade item.
 // This data is typically registered once by the product's manufacturer / supplier,
 // to be shared with other network participants, and remains largely static.
-// It can also be used for instance-level (lot) master data.
+// Instance-level (lot) master data is registered as lots of the product.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct Product<AccountId, Moment> {
     // The product ID would typically be a GS1 GTIN (Global Trade Item Number),
@@ -47,104 +57,1031 @@ pub struct Product<AccountId, Moment> {
     props: Option<Vec<ProductProperty>>,
     // Timestamp (approximate) at which the prodct was registered on-chain.
     registered: Moment,
+    // Lifecycle status of the product.
+    status: ProductStatus,
+}
+
+impl<AccountId, Moment> Product<AccountId, Moment> {
+    pub fn id(&self) -> &[u8] {
+        self.id.as_ref()
+    }
+
+    pub fn owner(&self) -> &AccountId {
+        &self.owner
+    }
+
+    pub fn props(&self) -> &Option<Vec<ProductProperty>> {
+        &self.props
+    }
+
+    pub fn registered(&self) -> &Moment {
+        &self.registered
+    }
+
+    pub fn status(&self) -> &ProductStatus {
+        &self.status
+    }
+}
+
+// Lifecycle status of a product.
+// A discontinued product is no longer supplied, but existing stock can still be traded,
+// whereas a recalled product must be withdrawn from the supply chain.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum ProductStatus {
+    Active,
+    Discontinued,
+    Recalled,
+}
+
+// Lot contains instance-level master data about a batch of a product,
+// e.g. items produced on the same line from the same raw materials.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Lot<Moment> {
+    // The lot ID (aka batch number) is unique for a given product.
+    id: LotId,
+    // The product (class) this lot belongs to.
+    product_id: ProductId,
+    // Timestamp at which the lot was produced.
+    produced: Moment,
+    // Timestamp at which the lot expires, if any.
+    expires: Option<Moment>,
+    // Number of trade items in the lot.
+    quantity: u64,
+    // Lot-specific properties e.g. harvest location, net weight.
+    props: Option<Vec<ProductProperty>>,
+    // Timestamp (approximate) at which the lot was registered on-chain.
+    registered: Moment,
+}
+
+// Certificate issued by an accredited certifier, attesting that a product
+// complies with a certification scheme during a validity window.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Certificate<AccountId, Moment> {
+    // The certificate ID is unique for a given product.
+    id: CertificateId,
+    // The certified product.
+    product_id: ProductId,
+    // The organization which issued the certificate.
+    certifier: AccountId,
+    // The certification scheme e.g. organic.
+    scheme: CertificationScheme,
+    // Timestamps at which the certificate becomes valid & expires.
+    valid_from: Moment,
+    valid_until: Moment,
+    // Hash of the (off-chain) certificate document.
+    doc_hash: [u8; 32],
+    // Timestamp (approximate) at which the certificate was issued on-chain.
+    issued: Moment,
+    // Timestamp (approximate) at which the certificate was revoked, if any.
+    revoked: Option<Moment>,
+}
+
+impl<AccountId, Moment> Certificate<AccountId, Moment> {
+    pub fn id(&self) -> &[u8] {
+        self.id.as_ref()
+    }
+
+    pub fn product_id(&self) -> &[u8] {
+        self.product_id.as_ref()
+    }
+
+    pub fn certifier(&self) -> &AccountId {
+        &self.certifier
+    }
+
+    pub fn scheme(&self) -> &[u8] {
+        self.scheme.as_ref()
+    }
+
+    pub fn valid_from(&self) -> &Moment {
+        &self.valid_from
+    }
+
+    pub fn valid_until(&self) -> &Moment {
+        &self.valid_until
+    }
+
+    pub fn doc_hash(&self) -> &[u8; 32] {
+        &self.doc_hash
+    }
+
+    pub fn issued(&self) -> &Moment {
+        &self.issued
+    }
+
+    pub fn revoked(&self) -> &Option<Moment> {
+        &self.revoked
+    }
+}
+
+impl<AccountId, Moment: PartialOrd> Certificate<AccountId, Moment> {
+    // A certificate is valid within its validity window, unless revoked.
+    pub fn is_valid_at(&self, now: &Moment) -> bool {
+        self.revoked.is_none() && self.valid_from <= *now && *now < self.valid_until
+    }
+}
+
+// A component of a product's bill of materials, e.g. 2 x Ingredient ABC
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct BomComponent {
+    // The (registered) product used as a component
+    product_id: ProductId,
+    // Number of units of the component used in one unit of the product
+    quantity: u32,
+}
+
+impl BomComponent {
+    pub fn new(product_id: &[u8], quantity: u32) -> Self {
+        Self {
+            product_id: product_id.to_vec(),
+            quantity,
+        }
+    }
+
+    pub fn product_id(&self) -> &[u8] {
+        self.product_id.as_ref()
+    }
+
+    pub fn quantity(&self) -> u32 {
+        self.quantity
+    }
+}
+
+// Typed value of a product property
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum PropValue {
+    Text(Vec<u8>),
+    Integer(i64),
+    Decimal(PropDecimal),
+    Boolean(bool),
+    // Milliseconds since the Unix epoch
+    Timestamp(u64),
+    Hash([u8; 32]),
+    UnitQuantity(UnitQuantity),
+}
+
+impl PropValue {
+    pub fn prop_type(&self) -> PropType {
+        match self {
+            PropValue::Text(_) => PropType::Text,
+            PropValue::Integer(_) => PropType::Integer,
+            PropValue::Decimal(_) => PropType::Decimal,
+            PropValue::Boolean(_) => PropType::Boolean,
+            PropValue::Timestamp(_) => PropType::Timestamp,
+            PropValue::Hash(_) => PropType::Hash,
+            PropValue::UnitQuantity(_) => PropType::UnitQuantity,
+        }
+    }
+}
+
+// Type of a product property value
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum PropType {
+    Text,
+    Integer,
+    Decimal,
+    Boolean,
+    Timestamp,
+    Hash,
+    UnitQuantity,
+}
+
+// Describes a property expected by a product property schema e.g. description: required text
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PropSchema {
+    // Name of the product property e.g. description
+    name: PropName,
+    // Type of the product property value
+    prop_type: PropType,
+    // Whether products registered against the schema must have the property
+    required: bool,
+    // Maximum length of a text value, if lower than the runtime limit
+    max_length: Option<u32>,
+}
+
+impl PropSchema {
+    pub fn new(name: &[u8], prop_type: PropType, required: bool, max_length: Option<u32>) -> Self {
+        Self {
+            name: name.to_vec(),
+            prop_type,
+            required,
+            max_length,
+        }
+    }
+
+    pub fn name(&self) -> &[u8] {
+        self.name.as_ref()
+    }
+
+    pub fn prop_type(&self) -> PropType {
+        self.prop_type
+    }
+
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    pub fn max_length(&self) -> Option<u32> {
+        self.max_length
+    }
+}
+
+// A quantity expressed in a given unit e.g. 12.5 kg
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct UnitQuantity {
+    pub value: PropDecimal,
+    pub unit: UnitCode,
+}
+
+// Contains a name-value pair for a product property e.g. description: Ingredient ABC
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ProductProperty {
+    // Name of the product property e.g. desc or description
+    name: PropName,
+    // Value of the product property e.g. Ingredient ABC
+    value: PropValue,
+}
+
+impl ProductProperty {
+    pub fn new(name: &[u8], value: PropValue) -> Self {
+        Self {
+            name: name.to_vec(),
+            value,
+        }
+    }
+
+    pub fn text(name: &[u8], value: &[u8]) -> Self {
+        Self::new(name, PropValue::Text(value.to_vec()))
+    }
+
+    pub fn name(&self) -> &[u8] {
+        self.name.as_ref()
+    }
+
+    pub fn value(&self) -> &PropValue {
+        &self.value
+    }
+}
+
+// Describes how the properties of a registered product are updated.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum ProductPropsUpdate {
+    // Replace the whole property set
+    Replace(Option<Vec<ProductProperty>>),
+    // Update properties with the same name, add the others
+    Patch(Vec<ProductProperty>),
+}
+
+// Keeps an earlier property set of a product, as it was before being changed.
+// The property set was in effect until the given block / timestamp,
+// at which it was replaced by the given account.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ProductRevision<AccountId, BlockNumber, Moment> {
+    props: Option<Vec<ProductProperty>>,
+    changed_by: AccountId,
+    block: BlockNumber,
+    timestamp: Moment,
+}
+
+impl<AccountId, BlockNumber, Moment> ProductRevision<AccountId, BlockNumber, Moment> {
+    pub fn props(&self) -> &Option<Vec<ProductProperty>> {
+        &self.props
+    }
+
+    pub fn changed_by(&self) -> &AccountId {
+        &self.changed_by
+    }
+
+    pub fn block(&self) -> &BlockNumber {
+        &self.block
+    }
+
+    pub fn timestamp(&self) -> &Moment {
+        &self.timestamp
+    }
+}
+
+// Storage version of the pallet, used to run storage migrations on runtime upgrades.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    V1_0_0,
+    V2_0_0,
+    V3_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
+// Allows other pallets to look up registered products.
+pub trait ProductProvider<AccountId> {
+    fn product_status(id: &ProductId) -> Option<ProductStatus>;
+    fn product_owner(id: &ProductId) -> Option<AccountId>;
+    // Whether the lot is registered, as a lot of the given product
+    fn lot_exists(product_id: &ProductId, lot_id: &LotId) -> bool;
+}
+
+impl<T: Trait> ProductProvider<T::AccountId> for Module<T> {
+    fn product_status(id: &ProductId) -> Option<ProductStatus> {
+        Self::product_by_id(id).map(|product| product.status)
+    }
+
+    fn product_owner(id: &ProductId) -> Option<T::AccountId> {
+        Self::owner_of(id)
+    }
+
+    fn lot_exists(product_id: &ProductId, lot_id: &LotId) -> bool {
+        <Lots<T>>::contains_key(product_id, lot_id)
+    }
+}
+
+// Provides the GS1 Company Prefix of an organization, if it has one.
+pub trait CompanyPrefixProvider<AccountId> {
+    fn company_prefix(org: &AccountId) -> Option<Vec<u8>>;
+}
+
+impl<AccountId> CompanyPrefixProvider<AccountId> for () {
+    fn company_prefix(_org: &AccountId) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+// Why an account cannot own products or shipments, or act on behalf of their owner.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum OwnerError {
+    OwnerNotOrganization,
+    SenderNotMemberOrDelegate,
+}
+
+// Tells whether accounts are registered organizations, and who can act on their behalf.
+pub trait OrganizationProvider<AccountId: PartialEq> {
+    fn is_organization(org: &AccountId) -> bool;
+    // Whether the account is a member, or an authorized delegate, of the organization
+    fn is_member_or_delegate(org: &AccountId, account: &AccountId) -> bool;
+
+    // The account can act on behalf of its own organization account,
+    // or of an organization it is a member or an authorized delegate of.
+    fn acts_for(account: &AccountId, org: &AccountId) -> bool {
+        account == org || Self::is_member_or_delegate(org, account)
+    }
+
+    // The owner must be an organization, on behalf of which the account can act.
+    fn validate_owner(account: &AccountId, owner: &AccountId) -> Result<(), OwnerError> {
+        if !Self::is_organization(owner) {
+            return Err(OwnerError::OwnerNotOrganization);
+        }
+        if !Self::acts_for(account, owner) {
+            return Err(OwnerError::SenderNotMemberOrDelegate);
+        }
+        Ok(())
+    }
+}
+
+// Handler called when a product has been recalled, e.g. to flag its shipments.
+pub trait OnProductRecall {
+    fn on_product_recall(id: &ProductId);
+    // Weight of handling the recall of the given product, charged to the recall
+    fn on_product_recall_weight(id: &ProductId) -> Weight;
+}
+
+impl OnProductRecall for () {
+    fn on_product_recall(_id: &ProductId) {}
+
+    fn on_product_recall_weight(_id: &ProductId) -> Weight {
+        0
+    }
+}
+
+pub trait Trait: system::Trait + timestamp::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    type OnProductRecall: OnProductRecall;
+    type Organizations: OrganizationProvider<Self::AccountId>;
+
+    // General constraints to limit data size
+    type ProductIdMaxLength: Get<u32>;
+    type ProductMaxProps: Get<u32>;
+    type ProductPropNameMaxLength: Get<u32>;
+    type ProductPropValueMaxLength: Get<u32>;
+    type LotIdMaxLength: Get<u32>;
+    // Maximum number of products registered in a single batch
+    type ProductBatchMaxSize: Get<u32>;
+    type CertificateIdMaxLength: Get<u32>;
+    type CertificationSchemeMaxLength: Get<u32>;
+    type SchemaIdMaxLength: Get<u32>;
+    type BomMaxComponents: Get<u32>;
+    // Maximum depth of the bill of materials graph that is walked (e.g. when checking for cycles)
+    type BomMaxDepth: Get<u32>;
+
+    // When enabled, product IDs must be GS1 GTINs matching the owner's GS1 Company Prefix
+    type EnforceGs1: Get<bool>;
+    type CompanyPrefixes: CompanyPrefixProvider<Self::AccountId>;
+
+    // Origin accrediting organizations to issue product certificates (e.g. root or a council),
+    // so that organizations cannot accredit themselves
+    type AccreditationOrigin: EnsureOrigin<Self::Origin>;
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as ProductRegistry {
+        pub Products get(fn product_by_id): map hasher(blake2_128_concat) ProductId => Option<Product<T::AccountId, T::Moment>>;
+        pub ProductsOfOrganization get(fn products_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<ProductId>;
+        pub OwnerOf get(fn owner_of): map hasher(blake2_128_concat) ProductId => Option<T::AccountId>;
+        pub ProductRevisions get(fn product_revisions): map hasher(blake2_128_concat) ProductId => Vec<ProductRevision<T::AccountId, T::BlockNumber, T::Moment>>;
+        pub Lots get(fn lot_by_id): double_map hasher(blake2_128_concat) ProductId, hasher(blake2_128_concat) LotId => Option<Lot<T::Moment>>;
+
+        // Product property schemas of organizations, and the schema products were registered against
+        pub Schemas get(fn schema_by_id): double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) SchemaId => Option<Vec<PropSchema>>;
+        pub SchemaOf get(fn schema_of): map hasher(blake2_128_concat) ProductId => Option<(T::AccountId, SchemaId)>;
+
+        pub Certificates get(fn certificate_by_id): double_map hasher(blake2_128_concat) ProductId, hasher(blake2_128_concat) CertificateId => Option<Certificate<T::AccountId, T::Moment>>;
+        // Organizations accredited to issue certificates of a certification scheme
+        pub Accreditations get(fn is_accredited): double_map hasher(blake2_128_concat) T::AccountId, hasher(blake2_128_concat) CertificationScheme => bool;
+
+        // Bill of materials: parent -> children (components), and children -> parents
+        pub ComponentsOf get(fn components_of): map hasher(blake2_128_concat) ProductId => Vec<BomComponent>;
+        pub UsedIn get(fn used_in): map hasher(blake2_128_concat) ProductId => Vec<ProductId>;
+
+        // Storage version of the pallet
+        StorageVersion build(|_: &GenesisConfig| Releases::V3_0_0): Releases;
+    }
 }
 
-// Contains a name-value pair for a product property e.g. description: Ingredient ABC
-#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
-pub struct ProductProperty {
-    // Name of the product property e.g. desc or description
-    name: PropName,
-    // Value of the product property e.g. Ingredient ABC
-    value: PropValue,
-}
+decl_event!(
+    pub enum Event<T>
+    where
+        AccountId = <T as system::Trait>::AccountId,
+    {
+        ProductRegistered(AccountId, ProductId, AccountId),
+        ProductTransferred(AccountId, ProductId, AccountId, AccountId),
+        ProductUpdated(AccountId, ProductId),
+        ProductDiscontinued(AccountId, ProductId),
+        ProductRecalled(AccountId, ProductId),
+        LotRegistered(AccountId, ProductId, LotId),
+        LotUpdated(AccountId, ProductId, LotId),
+        BillOfMaterialsSet(AccountId, ProductId),
+        SchemaRegistered(AccountId, SchemaId),
+        CertifierAccredited(AccountId, CertificationScheme),
+        AccreditationRevoked(AccountId, CertificationScheme),
+        CertificateIssued(AccountId, ProductId, CertificateId),
+        CertificateRevoked(AccountId, ProductId, CertificateId),
+    }
+);
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        ProductIdMissing,
+        ProductIdTooLong,
+        ProductIdExists,
+        ProductBatchTooLarge,
+        ProductIdUnknown,
+        ProductIdInvalidGtin,
+        ProductIdInvalidCheckDigit,
+        ProductIdCompanyPrefixMismatch,
+        ProductIdWithoutCompanyPrefix,
+        OrganizationMissingCompanyPrefix,
+        ProductNotOwnedBySender,
+        OwnerNotOrganization,
+        SenderNotMemberOrDelegate,
+        ProductAlreadyOwned,
+        ProductNotActive,
+        ProductAlreadyRecalled,
+        LotIdMissing,
+        LotIdTooLong,
+        LotIdExists,
+        LotIdUnknown,
+        BomTooManyComponents,
+        BomComponentUnknown,
+        BomComponentDuplicate,
+        BomComponentInvalidQuantity,
+        BomHasCycle,
+        BomTooDeep,
+        ProductTooManyProps,
+        ProductInvalidPropName,
+        ProductInvalidPropValue,
+        ProductInvalidPropUnit,
+        ProductMissingRequiredProp,
+        ProductUnknownProp,
+        ProductInvalidPropType,
+        SchemaIdMissing,
+        SchemaIdTooLong,
+        SchemaIdExists,
+        SchemaIdUnknown,
+        SchemaInvalidProp,
+        SchemaDuplicateProp,
+        CertificateIdMissing,
+        CertificateIdTooLong,
+        CertificateIdExists,
+        CertificateIdUnknown,
+        CertificateInvalidScheme,
+        CertificateInvalidValidity,
+        CertificateNotIssuedBySender,
+        CertificateAlreadyRevoked,
+        CertifierAlreadyAccredited,
+        CertifierNotAccredited
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+        fn deposit_event() = default;
+
+        const ProductIdMaxLength: u32 = T::ProductIdMaxLength::get();
+        const ProductMaxProps: u32 = T::ProductMaxProps::get();
+        const ProductPropNameMaxLength: u32 = T::ProductPropNameMaxLength::get();
+        const ProductPropValueMaxLength: u32 = T::ProductPropValueMaxLength::get();
+        const LotIdMaxLength: u32 = T::LotIdMaxLength::get();
+        const ProductBatchMaxSize: u32 = T::ProductBatchMaxSize::get();
+        const CertificateIdMaxLength: u32 = T::CertificateIdMaxLength::get();
+        const CertificationSchemeMaxLength: u32 = T::CertificationSchemeMaxLength::get();
+        const SchemaIdMaxLength: u32 = T::SchemaIdMaxLength::get();
+        const BomMaxComponents: u32 = T::BomMaxComponents::get();
+        const BomMaxDepth: u32 = T::BomMaxDepth::get();
+
+        fn on_runtime_upgrade() -> Weight {
+            migration::migrate_to_v3::<T>()
+        }
+
+        #[weight = 10_000]
+        pub fn register_product(
+            origin,
+            id: ProductId,
+            owner: T::AccountId,
+            props: Option<Vec<ProductProperty>>,
+            schema_id: Option<SchemaId>
+        ) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product (3 DB reads)
+            Self::validate_product_registration(&who, &id, &owner, &props, &schema_id)?;
+
+            // Add product (4 DB writes)
+            Self::insert_product(id.clone(), owner.clone(), props, schema_id);
+
+            Self::deposit_event(RawEvent::ProductRegistered(who, id, owner));
+
+            Ok(())
+        }
+
+        #[weight = 10_000 + (products.len() as Weight).saturating_mul(10_000)]
+        pub fn register_products(origin, products: Vec<ProductRegistration<T::AccountId>>) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            ensure!(
+                products.len() <= T::ProductBatchMaxSize::get() as usize,
+                Error::<T>::ProductBatchTooLarge
+            );
+
+            // Validate all products before registering any (3 DB reads per product)
+            for (idx, (id, owner, props, schema_id)) in products.iter().enumerate() {
+                ensure!(
+                    !products[..idx].iter().any(|(other_id, ..)| other_id == id),
+                    Error::<T>::ProductIdExists
+                );
+                Self::validate_product_registration(&who, id, owner, props, schema_id)?;
+            }
+
+            // Add products (4 DB writes per product)
+            for (id, owner, props, schema_id) in products {
+                Self::insert_product(id.clone(), owner.clone(), props, schema_id);
+                Self::deposit_event(RawEvent::ProductRegistered(who.clone(), id, owner));
+            }
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn transfer_product(origin, id: ProductId, new_owner: T::AccountId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product ID
+            Self::validate_product_id(&id)?;
+
+            // Check product exists (1 DB read)
+            let mut product = Self::product_by_id(&id).ok_or(Error::<T>::ProductIdUnknown)?;
+            let old_owner = product.owner.clone();
+
+            // Only the current owning organization, or its members & delegates, can transfer the product
+            ensure!(
+                T::Organizations::acts_for(&who, &old_owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+            ensure!(old_owner != new_owner, Error::<T>::ProductAlreadyOwned);
+            ensure!(
+                T::Organizations::is_organization(&new_owner),
+                Error::<T>::OwnerNotOrganization
+            );
+
+            product.owner = new_owner.clone();
+
+            // Update product & ownerOf (4 DB writes)
+            <Products<T>>::insert(&id, product);
+            <ProductsOfOrganization<T>>::mutate(&old_owner, |ids| ids.retain(|pid| pid != &id));
+            <ProductsOfOrganization<T>>::append(&new_owner, &id);
+            <OwnerOf<T>>::insert(&id, &new_owner);
+
+            // The schema of the previous owner no longer applies (1 DB write)
+            <SchemaOf<T>>::remove(&id);
+
+            Self::deposit_event(RawEvent::ProductTransferred(who, id, old_owner, new_owner));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn update_product_props(origin, id: ProductId, update: ProductPropsUpdate) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product ID
+            Self::validate_product_id(&id)?;
+
+            // Check product exists (1 DB read)
+            let mut product = Self::product_by_id(&id).ok_or(Error::<T>::ProductIdUnknown)?;
+
+            // Only the current owning organization, or its members & delegates, can update the product
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+
+            // Compute & validate new product props, against the product's schema if any (2 DB reads)
+            let props = Self::apply_props_update(&product.props, update);
+            let schema = Self::schema_of(&id)
+                .and_then(|(org, schema_id)| Self::schema_by_id(&org, &schema_id));
+            Self::validate_product_props(&props, schema.as_deref())?;
+
+            // Keep track of the earlier props
+            let revision = ProductRevision {
+                props: product.props,
+                changed_by: who.clone(),
+                block: <system::Module<T>>::block_number(),
+                timestamp: <timestamp::Module<T>>::now(),
+            };
+            product.props = props;
+
+            // Update product & revisions (2 DB writes)
+            <Products<T>>::insert(&id, product);
+            <ProductRevisions<T>>::append(&id, revision);
+
+            Self::deposit_event(RawEvent::ProductUpdated(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn discontinue_product(origin, id: ProductId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product ID
+            Self::validate_product_id(&id)?;
+
+            // Check product exists (1 DB read)
+            let mut product = Self::product_by_id(&id).ok_or(Error::<T>::ProductIdUnknown)?;
+
+            // Only the current owning organization, or its members & delegates, can discontinue an active product
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+            ensure!(product.status == ProductStatus::Active, Error::<T>::ProductNotActive);
+
+            product.status = ProductStatus::Discontinued;
+
+            // Update product (1 DB write)
+            <Products<T>>::insert(&id, product);
+
+            Self::deposit_event(RawEvent::ProductDiscontinued(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000u64.saturating_add(T::OnProductRecall::on_product_recall_weight(id))]
+        pub fn recall_product(origin, id: ProductId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product ID
+            Self::validate_product_id(&id)?;
+
+            // Check product exists (1 DB read)
+            let mut product = Self::product_by_id(&id).ok_or(Error::<T>::ProductIdUnknown)?;
+
+            // Only the current owning organization, or its members & delegates, can recall a product
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+            ensure!(product.status != ProductStatus::Recalled, Error::<T>::ProductAlreadyRecalled);
+
+            product.status = ProductStatus::Recalled;
+
+            // Update product (1 DB write)
+            <Products<T>>::insert(&id, product);
+
+            // Let other pallets react to the recall
+            T::OnProductRecall::on_product_recall(&id);
+
+            Self::deposit_event(RawEvent::ProductRecalled(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_lot(
+            origin,
+            product_id: ProductId,
+            lot_id: LotId,
+            produced: T::Moment,
+            expires: Option<T::Moment>,
+            quantity: u64,
+            props: Option<Vec<ProductProperty>>
+        ) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product & lot IDs
+            Self::validate_product_id(&product_id)?;
+            Self::validate_lot_id(&lot_id)?;
+
+            // Validate lot props
+            Self::validate_product_props(&props, None)?;
+
+            // Check product exists & is active (1 DB read)
+            let product = Self::product_by_id(&product_id).ok_or(Error::<T>::ProductIdUnknown)?;
+            ensure!(product.status == ProductStatus::Active, Error::<T>::ProductNotActive);
+
+            // Only the owning organization, or its members & delegates, can register lots of a product
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+
+            // Check lot doesn't exist yet (1 DB read)
+            ensure!(
+                !<Lots<T>>::contains_key(&product_id, &lot_id),
+                Error::<T>::LotIdExists
+            );
+
+            // Create a lot instance
+            let lot = Self::new_lot()
+                .identified_by(lot_id.clone())
+                .of_product(product_id.clone())
+                .produced_on(produced)
+                .expiring_on(expires)
+                .with_quantity(quantity)
+                .with_props(props)
+                .registered_on(<timestamp::Module<T>>::now())
+                .build();
+
+            // Add lot (1 DB write)
+            <Lots<T>>::insert(&product_id, &lot_id, lot);
+
+            Self::deposit_event(RawEvent::LotRegistered(who, product_id, lot_id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn update_lot(
+            origin,
+            product_id: ProductId,
+            lot_id: LotId,
+            expires: Option<T::Moment>,
+            quantity: u64,
+            props: Option<Vec<ProductProperty>>
+        ) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product & lot IDs
+            Self::validate_product_id(&product_id)?;
+            Self::validate_lot_id(&lot_id)?;
+
+            // Validate lot props
+            Self::validate_product_props(&props, None)?;
+
+            // Only the owning organization, or its members & delegates, can update lots of a product (1 DB read)
+            let product = Self::product_by_id(&product_id).ok_or(Error::<T>::ProductIdUnknown)?;
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+
+            // Check lot exists (1 DB read)
+            let mut lot = Self::lot_by_id(&product_id, &lot_id).ok_or(Error::<T>::LotIdUnknown)?;
+
+            lot.expires = expires;
+            lot.quantity = quantity;
+            lot.props = props;
+
+            // Update lot (1 DB write)
+            <Lots<T>>::insert(&product_id, &lot_id, lot);
+
+            Self::deposit_event(RawEvent::LotUpdated(who, product_id, lot_id));
+
+            Ok(())
+        }
+
+        // Worst case weight: 2 DB reads, then 1 DB read & a walk of the bill of materials graph
+        // per component, and 1 DB write & 1 DB write per previous & new component
+        #[weight = {
+            let components = components.len() as Weight;
+            T::DbWeight::get()
+                .reads_writes(
+                    components
+                        .saturating_mul(<Module<T>>::bom_walk_max_reads().saturating_add(1))
+                        .saturating_add(2),
+                    components
+                        .saturating_add(T::BomMaxComponents::get() as Weight)
+                        .saturating_add(1)
+                )
+                .saturating_add(10_000)
+        }]
+        pub fn set_bill_of_materials(origin, id: ProductId, components: Vec<BomComponent>) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate product ID
+            Self::validate_product_id(&id)?;
+
+            // Only the owning organization, or its members & delegates, can set the product's bill of materials (1 DB read)
+            let product = Self::product_by_id(&id).ok_or(Error::<T>::ProductIdUnknown)?;
+            ensure!(
+                T::Organizations::acts_for(&who, &product.owner),
+                Error::<T>::ProductNotOwnedBySender
+            );
+
+            // Validate components (1 DB read per component + graph walk)
+            Self::validate_bom_components(&id, &components)?;
+
+            // Storage writes
+            // --------------
+            // Unlink previous components (1 DB write per component)
+            for component in Self::components_of(&id) {
+                <UsedIn>::mutate(&component.product_id, |parents| parents.retain(|p| p != &id));
+            }
+            // Link new components (1 DB write per component)
+            for component in components.iter() {
+                <UsedIn>::append(&component.product_id, &id);
+            }
+            // Set bill of materials (1 DB write)
+            <ComponentsOf>::insert(&id, components);
+
+            Self::deposit_event(RawEvent::BillOfMaterialsSet(who, id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_schema(
+            origin,
+            org: T::AccountId,
+            id: SchemaId,
+            props: Vec<PropSchema>
+        ) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate organization, and that the sender can register schemas on its behalf
+            Self::validate_owner(&who, &org)?;
 
-impl ProductProperty {
-    pub fn new(name: &[u8], value: &[u8]) -> Self {
-        Self {
-            name: name.to_vec(),
-            value: value.to_vec(),
+            // Validate schema ID & props
+            ensure!(!id.is_empty(), Error::<T>::SchemaIdMissing);
+            ensure!(
+                id.len() <= T::SchemaIdMaxLength::get() as usize,
+                Error::<T>::SchemaIdTooLong
+            );
+            Self::validate_schema_props(&props)?;
+
+            // Check schema doesn't exist yet for the organization (1 DB read)
+            ensure!(
+                !<Schemas<T>>::contains_key(&org, &id),
+                Error::<T>::SchemaIdExists
+            );
+
+            // Add schema (1 DB write)
+            <Schemas<T>>::insert(&org, &id, props);
+
+            Self::deposit_event(RawEvent::SchemaRegistered(org, id));
+
+            Ok(())
         }
-    }
 
-    pub fn name(&self) -> &[u8] {
-        self.name.as_ref()
-    }
+        #[weight = 10_000]
+        pub fn accredit_certifier(origin, certifier: T::AccountId, scheme: CertificationScheme) -> dispatch::DispatchResult {
+            T::AccreditationOrigin::ensure_origin(origin)?;
 
-    pub fn value(&self) -> &[u8] {
-        self.value.as_ref()
-    }
-}
+            ensure!(T::Organizations::is_organization(&certifier), Error::<T>::OwnerNotOrganization);
+            Self::validate_certification_scheme(&scheme)?;
 
-pub trait Trait: system::Trait + timestamp::Trait {
-    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
-    type CreateRoleOrigin: EnsureOrigin<Self::Origin>;
-}
+            // Check certifier isn't accredited yet (1 DB read)
+            ensure!(
+                !Self::is_accredited(&certifier, &scheme),
+                Error::<T>::CertifierAlreadyAccredited
+            );
 
-decl_storage! {
-    trait Store for Module<T: Trait> as ProductRegistry {
-        pub Products get(fn product_by_id): map hasher(blake2_128_concat) ProductId => Option<Product<T::AccountId, T::Moment>>;
-        pub ProductsOfOrganization get(fn products_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<ProductId>;
-        pub OwnerOf get(fn owner_of): map hasher(blake2_128_concat) ProductId => Option<T::AccountId>;
-    }
-}
+            // Add accreditation (1 DB write)
+            <Accreditations<T>>::insert(&certifier, &scheme, true);
 
-decl_event!(
-    pub enum Event<T>
-    where
-        AccountId = <T as system::Trait>::AccountId,
-    {
-        ProductRegistered(AccountId, ProductId, AccountId),
-    }
-);
+            Self::deposit_event(RawEvent::CertifierAccredited(certifier, scheme));
 
-decl_error! {
-    pub enum Error for Module<T: Trait> {
-        ProductIdMissing,
-        ProductIdTooLong,
-        ProductIdExists,
-        ProductTooManyProps,
-        ProductInvalidPropName,
-        ProductInvalidPropValue
-    }
-}
+            Ok(())
+        }
 
-decl_module! {
-    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-        type Error = Error<T>;
-        fn deposit_event() = default;
+        #[weight = 10_000]
+        pub fn revoke_accreditation(origin, certifier: T::AccountId, scheme: CertificationScheme) -> dispatch::DispatchResult {
+            T::AccreditationOrigin::ensure_origin(origin)?;
+
+            // Check certifier is accredited (1 DB read)
+            ensure!(
+                Self::is_accredited(&certifier, &scheme),
+                Error::<T>::CertifierNotAccredited
+            );
+
+            // Remove accreditation (1 DB write), the certificates already issued staying valid
+            <Accreditations<T>>::remove(&certifier, &scheme);
+
+            Self::deposit_event(RawEvent::AccreditationRevoked(certifier, scheme));
+
+            Ok(())
+        }
 
         #[weight = 10_000]
-        pub fn register_product(origin, id: ProductId, owner: T::AccountId, props: Option<Vec<ProductProperty>>) -> dispatch::DispatchResult {
-            T::CreateRoleOrigin::ensure_origin(origin.clone())?;
+        pub fn issue_certificate(
+            origin,
+            certifier: T::AccountId,
+            product_id: ProductId,
+            certificate_id: CertificateId,
+            scheme: CertificationScheme,
+            valid_from: T::Moment,
+            valid_until: T::Moment,
+            doc_hash: [u8; 32]
+        ) -> dispatch::DispatchResult {
             let who = ensure_signed(origin)?;
 
-            // Validate product ID
-            Self::validate_product_id(&id)?;
+            // Validate certifier organization, and that the sender can issue certificates on its behalf
+            Self::validate_owner(&who, &certifier)?;
 
-            // Validate product props
-            Self::validate_product_props(&props)?;
+            // Validate certificate
+            Self::validate_certificate_id(&certificate_id)?;
+            Self::validate_certification_scheme(&scheme)?;
+            ensure!(valid_from < valid_until, Error::<T>::CertificateInvalidValidity);
 
-            // Check product doesn't exist yet (1 DB read)
-            Self::validate_new_product(&id)?;
+            // Only accredited certifiers can certify products against the scheme (1 DB read)
+            ensure!(
+                Self::is_accredited(&certifier, &scheme),
+                Error::<T>::CertifierNotAccredited
+            );
 
-            // TODO: if organization has an attribute w/ GS1 Company prefix,
-            //       additional validation could be applied to the product ID
-            //       to ensure its validity (same company prefix as org).
+            // Check product exists & certificate doesn't exist yet (2 DB reads)
+            ensure!(
+                <Products<T>>::contains_key(&product_id),
+                Error::<T>::ProductIdUnknown
+            );
+            ensure!(
+                !<Certificates<T>>::contains_key(&product_id, &certificate_id),
+                Error::<T>::CertificateIdExists
+            );
 
-            // Create a product instance
-            let product = Self::new_product()
-                .identified_by(id.clone())
-                .owned_by(owner.clone())
-                .registered_on(<timestamp::Module<T>>::now())
-                .with_props(props)
-                .build();
+            let certificate = Certificate {
+                id: certificate_id.clone(),
+                product_id: product_id.clone(),
+                certifier: certifier.clone(),
+                scheme,
+                valid_from,
+                valid_until,
+                doc_hash,
+                issued: <timestamp::Module<T>>::now(),
+                revoked: None,
+            };
 
-            // Add product & ownerOf (3 DB writes)
-            <Products<T>>::insert(&id, product);
-            <ProductsOfOrganization<T>>::append(&owner, &id);
-            <OwnerOf<T>>::insert(&id, &owner);
+            // Add certificate (1 DB write)
+            <Certificates<T>>::insert(&product_id, &certificate_id, certificate);
 
-            Self::deposit_event(RawEvent::ProductRegistered(who, id, owner));
+            Self::deposit_event(RawEvent::CertificateIssued(certifier, product_id, certificate_id));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn revoke_certificate(origin, product_id: ProductId, certificate_id: CertificateId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check certificate exists (1 DB read)
+            let mut certificate = Self::certificate_by_id(&product_id, &certificate_id)
+                .ok_or(Error::<T>::CertificateIdUnknown)?;
+
+            // Only the issuing certifier, or its members & delegates, can revoke the certificate
+            ensure!(
+                T::Organizations::acts_for(&who, &certificate.certifier),
+                Error::<T>::CertificateNotIssuedBySender
+            );
+            ensure!(certificate.revoked.is_none(), Error::<T>::CertificateAlreadyRevoked);
+
+            certificate.revoked = Some(<timestamp::Module<T>>::now());
+            let certifier = certificate.certifier.clone();
+
+            // Update certificate (1 DB write)
+            <Certificates<T>>::insert(&product_id, &certificate_id, certificate);
+
+            Self::deposit_event(RawEvent::CertificateRevoked(certifier, product_id, certificate_id));
 
             Ok(())
         }
@@ -157,16 +1094,186 @@ impl<T: Trait> Module<T> {
         ProductBuilder::<T::AccountId, T::Moment>::default()
     }
 
+    fn new_lot() -> LotBuilder<T::Moment> {
+        LotBuilder::<T::Moment>::default()
+    }
+
+    fn validate_product_registration(
+        who: &T::AccountId,
+        id: &ProductId,
+        owner: &T::AccountId,
+        props: &Option<Vec<ProductProperty>>,
+        schema_id: &Option<SchemaId>,
+    ) -> Result<(), Error<T>> {
+        // Validate product ID
+        Self::validate_product_id(id)?;
+
+        // Validate owner, and that the sender can register products on its behalf
+        Self::validate_owner(who, owner)?;
+
+        // Validate product props, against the owner's schema if any (1 DB read)
+        let schema = match schema_id {
+            Some(schema_id) => {
+                Some(Self::schema_by_id(owner, schema_id).ok_or(Error::<T>::SchemaIdUnknown)?)
+            }
+            None => None,
+        };
+        Self::validate_product_props(props, schema.as_deref())?;
+
+        // Check product doesn't exist yet (1 DB read)
+        Self::validate_new_product(id)?;
+
+        // Validate product ID against GS1 standard, if enabled (1 DB read)
+        Self::validate_gs1_product_id(id, owner)
+    }
+
+    fn insert_product(
+        id: ProductId,
+        owner: T::AccountId,
+        props: Option<Vec<ProductProperty>>,
+        schema_id: Option<SchemaId>,
+    ) {
+        // Create a product instance
+        let product = Self::new_product()
+            .identified_by(id.clone())
+            .owned_by(owner.clone())
+            .registered_on(<timestamp::Module<T>>::now())
+            .with_props(props)
+            .build();
+
+        // Add product & ownerOf (3 DB writes)
+        <Products<T>>::insert(&id, product);
+        <ProductsOfOrganization<T>>::append(&owner, &id);
+        <OwnerOf<T>>::insert(&id, &owner);
+        // Keep track of the product's schema (1 DB write)
+        if let Some(schema_id) = schema_id {
+            <SchemaOf<T>>::insert(&id, (owner, schema_id));
+        }
+    }
+
+    fn apply_props_update(
+        props: &Option<Vec<ProductProperty>>,
+        update: ProductPropsUpdate,
+    ) -> Option<Vec<ProductProperty>> {
+        match update {
+            ProductPropsUpdate::Replace(new_props) => new_props,
+            ProductPropsUpdate::Patch(patch) => {
+                let mut props = props.clone().unwrap_or_default();
+                for prop in patch {
+                    match props.iter_mut().find(|p| p.name == prop.name) {
+                        Some(existing) => existing.value = prop.value,
+                        None => props.push(prop),
+                    }
+                }
+                Some(props)
+            }
+        }
+    }
+
+    pub fn validate_owner(who: &T::AccountId, owner: &T::AccountId) -> Result<(), Error<T>> {
+        T::Organizations::validate_owner(who, owner).map_err(|e| match e {
+            OwnerError::OwnerNotOrganization => Error::<T>::OwnerNotOrganization,
+            OwnerError::SenderNotMemberOrDelegate => Error::<T>::SenderNotMemberOrDelegate,
+        })
+    }
+
     pub fn validate_product_id(id: &[u8]) -> Result<(), Error<T>> {
         // Basic product ID validation
         ensure!(!id.is_empty(), Error::<T>::ProductIdMissing);
         ensure!(
-            id.len() <= PRODUCT_ID_MAX_LENGTH,
+            id.len() <= T::ProductIdMaxLength::get() as usize,
             Error::<T>::ProductIdTooLong
         );
         Ok(())
     }
 
+    pub fn validate_gs1_product_id(id: &[u8], owner: &T::AccountId) -> Result<(), Error<T>> {
+        if !T::EnforceGs1::get() {
+            return Ok(());
+        }
+
+        // GTIN format & check digit validation
+        ensure!(gs1::is_valid_format(id), Error::<T>::ProductIdInvalidGtin);
+        ensure!(
+            gs1::is_valid_check_digit(id),
+            Error::<T>::ProductIdInvalidCheckDigit
+        );
+
+        // GTIN must start with the owning organization's company prefix,
+        // which GTIN-8 cannot be checked against
+        ensure!(
+            gs1::has_company_prefix(id),
+            Error::<T>::ProductIdWithoutCompanyPrefix
+        );
+        let prefix = T::CompanyPrefixes::company_prefix(owner)
+            .filter(|prefix| !prefix.is_empty())
+            .ok_or(Error::<T>::OrganizationMissingCompanyPrefix)?;
+        ensure!(
+            gs1::to_gtin13(id).starts_with(&prefix),
+            Error::<T>::ProductIdCompanyPrefixMismatch
+        );
+        Ok(())
+    }
+
+    pub fn validate_lot_id(id: &[u8]) -> Result<(), Error<T>> {
+        // Basic lot ID validation
+        ensure!(!id.is_empty(), Error::<T>::LotIdMissing);
+        ensure!(
+            id.len() <= T::LotIdMaxLength::get() as usize,
+            Error::<T>::LotIdTooLong
+        );
+        Ok(())
+    }
+
+    pub fn validate_bom_components(id: &ProductId, components: &[BomComponent]) -> Result<(), Error<T>> {
+        ensure!(
+            components.len() <= T::BomMaxComponents::get() as usize,
+            Error::<T>::BomTooManyComponents
+        );
+        for (idx, component) in components.iter().enumerate() {
+            ensure!(
+                component.quantity > 0,
+                Error::<T>::BomComponentInvalidQuantity
+            );
+            ensure!(
+                !components[..idx]
+                    .iter()
+                    .any(|c| c.product_id == component.product_id),
+                Error::<T>::BomComponentDuplicate
+            );
+            ensure!(
+                <Products<T>>::contains_key(&component.product_id),
+                Error::<T>::BomComponentUnknown
+            );
+
+            // The product must not be (in)directly a component of its own components
+            ensure!(component.product_id != *id, Error::<T>::BomHasCycle);
+            let (descendants, truncated) =
+                Self::walk_bom(&component.product_id, T::BomMaxDepth::get(), true);
+            ensure!(!descendants.contains(id), Error::<T>::BomHasCycle);
+            ensure!(!truncated, Error::<T>::BomTooDeep);
+        }
+        Ok(())
+    }
+
+    pub fn validate_certificate_id(id: &[u8]) -> Result<(), Error<T>> {
+        // Basic certificate ID validation
+        ensure!(!id.is_empty(), Error::<T>::CertificateIdMissing);
+        ensure!(
+            id.len() <= T::CertificateIdMaxLength::get() as usize,
+            Error::<T>::CertificateIdTooLong
+        );
+        Ok(())
+    }
+
+    pub fn validate_certification_scheme(scheme: &[u8]) -> Result<(), Error<T>> {
+        ensure!(
+            !scheme.is_empty() && scheme.len() <= T::CertificationSchemeMaxLength::get() as usize,
+            Error::<T>::CertificateInvalidScheme
+        );
+        Ok(())
+    }
+
     pub fn validate_new_product(id: &[u8]) -> Result<(), Error<T>> {
         // Product existence check
         ensure!(
@@ -176,25 +1283,216 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
-    pub fn validate_product_props(props: &Option<Vec<ProductProperty>>) -> Result<(), Error<T>> {
+    pub fn validate_product_props(
+        props: &Option<Vec<ProductProperty>>,
+        schema: Option<&[PropSchema]>,
+    ) -> Result<(), Error<T>> {
         if let Some(props) = props {
             ensure!(
-                props.len() <= PRODUCT_MAX_PROPS,
+                props.len() <= T::ProductMaxProps::get() as usize,
                 Error::<T>::ProductTooManyProps,
             );
             for prop in props {
                 ensure!(
-                    prop.name().len() <= PRODUCT_PROP_NAME_MAX_LENGTH,
+                    prop.name().len() <= T::ProductPropNameMaxLength::get() as usize,
                     Error::<T>::ProductInvalidPropName
                 );
+                Self::validate_prop_value(prop.value())?;
+            }
+        }
+        if let Some(schema) = schema {
+            Self::validate_props_against_schema(props.as_deref().unwrap_or_default(), schema)?;
+        }
+        Ok(())
+    }
+
+    // Props must all be described by the schema, with the expected type & length,
+    // and the props required by the schema must be present.
+    fn validate_props_against_schema(props: &[ProductProperty], schema: &[PropSchema]) -> Result<(), Error<T>> {
+        for prop in props {
+            let prop_schema = schema
+                .iter()
+                .find(|s| s.name == prop.name)
+                .ok_or(Error::<T>::ProductUnknownProp)?;
+            ensure!(
+                prop.value.prop_type() == prop_schema.prop_type,
+                Error::<T>::ProductInvalidPropType
+            );
+            if let (PropValue::Text(text), Some(max_length)) = (&prop.value, prop_schema.max_length) {
                 ensure!(
-                    prop.value().len() <= PRODUCT_PROP_VALUE_MAX_LENGTH,
+                    text.len() <= max_length as usize,
                     Error::<T>::ProductInvalidPropValue
                 );
             }
         }
+        for prop_schema in schema.iter().filter(|s| s.required) {
+            ensure!(
+                props.iter().any(|p| p.name == prop_schema.name),
+                Error::<T>::ProductMissingRequiredProp
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_schema_props(props: &[PropSchema]) -> Result<(), Error<T>> {
+        ensure!(
+            props.len() <= T::ProductMaxProps::get() as usize,
+            Error::<T>::ProductTooManyProps,
+        );
+        for (idx, prop) in props.iter().enumerate() {
+            ensure!(
+                !prop.name.is_empty()
+                    && prop.name.len() <= T::ProductPropNameMaxLength::get() as usize,
+                Error::<T>::SchemaInvalidProp
+            );
+            ensure!(
+                prop.max_length.unwrap_or(0) <= T::ProductPropValueMaxLength::get(),
+                Error::<T>::SchemaInvalidProp
+            );
+            ensure!(
+                !props[..idx].iter().any(|p| p.name == prop.name),
+                Error::<T>::SchemaDuplicateProp
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_prop_value(value: &PropValue) -> Result<(), Error<T>> {
+        match value {
+            PropValue::Text(text) => ensure!(
+                text.len() <= T::ProductPropValueMaxLength::get() as usize,
+                Error::<T>::ProductInvalidPropValue
+            ),
+            PropValue::UnitQuantity(quantity) => ensure!(
+                Self::is_valid_unit_code(&quantity.unit),
+                Error::<T>::ProductInvalidPropUnit
+            ),
+            PropValue::Integer(_)
+            | PropValue::Decimal(_)
+            | PropValue::Boolean(_)
+            | PropValue::Timestamp(_)
+            | PropValue::Hash(_) => (),
+        }
         Ok(())
     }
+
+    // UCUM unit codes are made of printable ASCII characters, without spaces
+    fn is_valid_unit_code(unit: &[u8]) -> bool {
+        !unit.is_empty()
+            && unit.len() <= T::ProductPropValueMaxLength::get() as usize
+            && unit.iter().all(|c| c.is_ascii_graphic())
+    }
+}
+
+// Product queries (e.g. for the runtime API)
+impl<T: Trait> Module<T> {
+    // Returns a page of at most `limit` products of the given organization,
+    // starting after the `cursor` product (or from the start if none is given),
+    // together with the cursor of the next page, if any.
+    // An unknown cursor (e.g. a product transferred in the meantime) yields an empty page.
+    pub fn products_of_organization_page(
+        org: &T::AccountId,
+        cursor: Option<ProductId>,
+        limit: u32,
+    ) -> (Vec<Product<T::AccountId, T::Moment>>, Option<ProductId>) {
+        let ids = Self::products_of_org(org);
+        let start = match cursor {
+            Some(cursor) => match ids.iter().position(|id| *id == cursor) {
+                Some(idx) => idx + 1,
+                None => return (Vec::new(), None),
+            },
+            None => 0,
+        };
+        let end = ids.len().min(start.saturating_add(limit as usize));
+
+        let products = ids[start.min(end)..end]
+            .iter()
+            .filter_map(|id| Self::product_by_id(id))
+            .collect();
+        let next = if end < ids.len() && end > start {
+            Some(ids[end - 1].clone())
+        } else {
+            None
+        };
+        (products, next)
+    }
+}
+
+// Certificate queries
+impl<T: Trait> Module<T> {
+    // Returns the certificates of the given product which are currently valid.
+    pub fn valid_certificates(product_id: &ProductId) -> Vec<Certificate<T::AccountId, T::Moment>> {
+        let now = <timestamp::Module<T>>::now();
+        <Certificates<T>>::iter_prefix_values(product_id)
+            .filter(|certificate| certificate.is_valid_at(&now))
+            .collect()
+    }
+}
+
+// Bill of materials queries
+impl<T: Trait> Module<T> {
+    // Returns the products (in)directly used as components of the given product,
+    // down to the given depth.
+    pub fn components_tree(id: &ProductId, max_depth: u32) -> Vec<ProductId> {
+        Self::walk_bom(id, max_depth.min(T::BomMaxDepth::get()), true).0
+    }
+
+    // Returns the products (in)directly containing the given product as a component,
+    // up to the given depth.
+    pub fn products_containing(id: &ProductId, max_depth: u32) -> Vec<ProductId> {
+        Self::walk_bom(id, max_depth.min(T::BomMaxDepth::get()), false).0
+    }
+
+    // Upper bound of the DB reads of a walk of the bill of materials graph, down to `BomMaxDepth`:
+    // up to BomMaxComponents^BomMaxDepth products visited, each read again to tell whether
+    // the walk was cut short.
+    pub fn bom_walk_max_reads() -> Weight {
+        (T::BomMaxComponents::get() as Weight)
+            .saturating_pow(T::BomMaxDepth::get())
+            .saturating_mul(2)
+    }
+
+    fn bom_neighbours(id: &ProductId, downward: bool) -> Vec<ProductId> {
+        if downward {
+            Self::components_of(id)
+                .into_iter()
+                .map(|component| component.product_id)
+                .collect()
+        } else {
+            Self::used_in(id)
+        }
+    }
+
+    // Walks the bill of materials graph breadth-first, either downward (components)
+    // or upward (products containing it), from the given product up to the given depth.
+    // Returns the visited products (in visiting order), and whether the walk was cut short by the depth limit.
+    fn walk_bom(id: &ProductId, max_depth: u32, downward: bool) -> (Vec<ProductId>, bool) {
+        let mut visited: Vec<ProductId> = Vec::new();
+        let mut seen: BTreeSet<ProductId> = BTreeSet::new();
+        seen.insert(id.clone());
+        let mut frontier = vec![id.clone()];
+
+        for _ in 0..max_depth {
+            let mut next = Vec::new();
+            for node in frontier.iter() {
+                for neighbour in Self::bom_neighbours(node, downward) {
+                    if seen.insert(neighbour.clone()) {
+                        visited.push(neighbour.clone());
+                        next.push(neighbour);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return (visited, false);
+            }
+            frontier = next;
+        }
+
+        let truncated = frontier
+            .iter()
+            .any(|node| !Self::bom_neighbours(node, downward).is_empty());
+        (visited, truncated)
+    }
 }
 
 #[derive(Default)]
@@ -240,6 +1538,73 @@ where
             owner: self.owner,
             props: self.props,
             registered: self.registered,
+            status: ProductStatus::Active,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LotBuilder<Moment>
+where
+    Moment: Default,
+{
+    id: LotId,
+    product_id: ProductId,
+    produced: Moment,
+    expires: Option<Moment>,
+    quantity: u64,
+    props: Option<Vec<ProductProperty>>,
+    registered: Moment,
+}
+
+impl<Moment> LotBuilder<Moment>
+where
+    Moment: Default,
+{
+    pub fn identified_by(mut self, id: LotId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn of_product(mut self, product_id: ProductId) -> Self {
+        self.product_id = product_id;
+        self
+    }
+
+    pub fn produced_on(mut self, produced: Moment) -> Self {
+        self.produced = produced;
+        self
+    }
+
+    pub fn expiring_on(mut self, expires: Option<Moment>) -> Self {
+        self.expires = expires;
+        self
+    }
+
+    pub fn with_quantity(mut self, quantity: u64) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn with_props(mut self, props: Option<Vec<ProductProperty>>) -> Self {
+        self.props = props;
+        self
+    }
+
+    pub fn registered_on(mut self, registered: Moment) -> Self {
+        self.registered = registered;
+        self
+    }
+
+    pub fn build(self) -> Lot<Moment> {
+        Lot::<Moment> {
+            id: self.id,
+            product_id: self.product_id,
+            produced: self.produced,
+            expires: self.expires,
+            quantity: self.quantity,
+            props: self.props,
+            registered: self.registered,
         }
     }
 }
@@ -0,0 +1,127 @@
+// Storage migrations run on runtime upgrades
+
+use crate::*;
+use frame_support::{
+    storage::{IterableStorageDoubleMap, IterableStorageMap},
+    traits::Get,
+    StorageValue,
+};
+
+// Product property as stored before typed values were introduced
+#[derive(Decode)]
+struct ProductPropertyV2 {
+    name: PropName,
+    value: Vec<u8>,
+}
+
+impl From<ProductPropertyV2> for ProductProperty {
+    fn from(prop: ProductPropertyV2) -> Self {
+        ProductProperty::new(&prop.name, PropValue::Text(prop.value))
+    }
+}
+
+fn props_to_v3(props: Option<Vec<ProductPropertyV2>>) -> Option<Vec<ProductProperty>> {
+    props.map(|props| props.into_iter().map(Into::into).collect())
+}
+
+// Product as stored before lifecycle status was introduced
+#[derive(Decode)]
+struct ProductV1<AccountId, Moment> {
+    id: ProductId,
+    owner: AccountId,
+    props: Option<Vec<ProductPropertyV2>>,
+    registered: Moment,
+}
+
+// Product as stored before typed property values were introduced
+#[derive(Decode)]
+struct ProductV2<AccountId, Moment> {
+    id: ProductId,
+    owner: AccountId,
+    props: Option<Vec<ProductPropertyV2>>,
+    registered: Moment,
+    status: ProductStatus,
+}
+
+#[derive(Decode)]
+struct LotV2<Moment> {
+    id: LotId,
+    product_id: ProductId,
+    produced: Moment,
+    expires: Option<Moment>,
+    quantity: u64,
+    props: Option<Vec<ProductPropertyV2>>,
+    registered: Moment,
+}
+
+#[derive(Decode)]
+struct ProductRevisionV2<AccountId, BlockNumber, Moment> {
+    props: Option<Vec<ProductPropertyV2>>,
+    changed_by: AccountId,
+    block: BlockNumber,
+    timestamp: Moment,
+}
+
+// Migrates storage to the latest version:
+// - V2 adds the lifecycle status to all products, registered products are considered active.
+// - V3 converts all existing property values to text values, in products, product revisions & lots.
+pub fn migrate_to_v3<T: Trait>() -> Weight {
+    match StorageVersion::get() {
+        Releases::V1_0_0 => {
+            <Products<T>>::translate::<ProductV1<T::AccountId, T::Moment>, _>(|_id, product| {
+                Some(Product {
+                    id: product.id,
+                    owner: product.owner,
+                    props: props_to_v3(product.props),
+                    registered: product.registered,
+                    status: ProductStatus::Active,
+                })
+            })
+        }
+        Releases::V2_0_0 => {
+            <Products<T>>::translate::<ProductV2<T::AccountId, T::Moment>, _>(|_id, product| {
+                Some(Product {
+                    id: product.id,
+                    owner: product.owner,
+                    props: props_to_v3(product.props),
+                    registered: product.registered,
+                    status: product.status,
+                })
+            })
+        }
+        Releases::V3_0_0 => return 0,
+    }
+
+    <ProductRevisions<T>>::translate::<Vec<ProductRevisionV2<T::AccountId, T::BlockNumber, T::Moment>>, _>(
+        |_id, revisions| {
+            Some(
+                revisions
+                    .into_iter()
+                    .map(|revision| ProductRevision {
+                        props: props_to_v3(revision.props),
+                        changed_by: revision.changed_by,
+                        block: revision.block,
+                        timestamp: revision.timestamp,
+                    })
+                    .collect(),
+            )
+        },
+    );
+    <Lots<T>>::translate::<LotV2<T::Moment>, _>(|_product_id, _lot_id, lot| {
+        Some(Lot {
+            id: lot.id,
+            product_id: lot.product_id,
+            produced: lot.produced,
+            expires: lot.expires,
+            quantity: lot.quantity,
+            props: props_to_v3(lot.props),
+            registered: lot.registered,
+        })
+    });
+    StorageVersion::put(Releases::V3_0_0);
+
+    let count = (<Products<T>>::iter().count()
+        + <ProductRevisions<T>>::iter().count()
+        + <Lots<T>>::iter().count()) as Weight;
+    T::DbWeight::get().reads_writes(count + 1, count + 1)
+}
@@ -1,12 +1,14 @@
 // Creating mock runtime here
 
-use crate::{Module, Trait};
-use core::marker::PhantomData;
+use crate::{CompanyPrefixProvider, Module, OrganizationProvider, Trait};
+use core::cell::RefCell;
 use frame_support::{
-    impl_outer_event, impl_outer_origin, parameter_types, traits::EnsureOrigin, weights::Weight,
+    impl_outer_event, impl_outer_origin, parameter_types,
+    traits::Get,
+    weights::Weight,
 };
+use std::collections::BTreeMap;
 use frame_system as system;
-use frame_system::RawOrigin;
 use sp_core::{sr25519, Pair, H256};
 use sp_runtime::{
     testing::Header,
@@ -37,6 +39,17 @@ parameter_types! {
     pub const MaximumBlockWeight: Weight = 1024;
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const ProductIdMaxLength: u32 = 36;
+    pub const ProductMaxProps: u32 = 3;
+    pub const ProductPropNameMaxLength: u32 = 10;
+    pub const ProductPropValueMaxLength: u32 = 20;
+    pub const LotIdMaxLength: u32 = 20;
+    pub const ProductBatchMaxSize: u32 = 3;
+    pub const CertificateIdMaxLength: u32 = 20;
+    pub const CertificationSchemeMaxLength: u32 = 10;
+    pub const SchemaIdMaxLength: u32 = 10;
+    pub const BomMaxComponents: u32 = 5;
+    pub const BomMaxDepth: u32 = 3;
 }
 
 impl system::Trait for Test {
@@ -76,21 +89,78 @@ impl timestamp::Trait for Test {
 
 impl Trait for Test {
     type Event = TestEvent;
-    type CreateRoleOrigin = MockOrigin<Test>;
+    type OnProductRecall = ();
+    type Organizations = MockOrganizations;
+    type ProductIdMaxLength = ProductIdMaxLength;
+    type ProductMaxProps = ProductMaxProps;
+    type ProductPropNameMaxLength = ProductPropNameMaxLength;
+    type ProductPropValueMaxLength = ProductPropValueMaxLength;
+    type LotIdMaxLength = LotIdMaxLength;
+    type ProductBatchMaxSize = ProductBatchMaxSize;
+    type CertificateIdMaxLength = CertificateIdMaxLength;
+    type CertificationSchemeMaxLength = CertificationSchemeMaxLength;
+    type SchemaIdMaxLength = SchemaIdMaxLength;
+    type BomMaxComponents = BomMaxComponents;
+    type BomMaxDepth = BomMaxDepth;
+    type EnforceGs1 = MockGs1;
+    type CompanyPrefixes = MockGs1;
+    type AccreditationOrigin = system::EnsureRoot<sr25519::Public>;
 }
 
 pub type ProductRegistry = Module<Test>;
 pub type System = system::Module<Test>;
 pub type Timestamp = timestamp::Module<Test>;
 
-pub struct MockOrigin<T>(PhantomData<T>);
+thread_local! {
+    static ENFORCE_GS1: RefCell<bool> = RefCell::new(false);
+    static COMPANY_PREFIXES: RefCell<BTreeMap<sr25519::Public, Vec<u8>>> = RefCell::new(BTreeMap::new());
+    static ORGANIZATIONS: RefCell<BTreeMap<sr25519::Public, Vec<sr25519::Public>>> = RefCell::new(BTreeMap::new());
+}
+
+// Stubs the GS1 mode & the organizations' GS1 Company Prefix attributes.
+pub struct MockGs1;
+
+impl MockGs1 {
+    pub fn enforce(enforce: bool) {
+        ENFORCE_GS1.with(|v| *v.borrow_mut() = enforce);
+    }
+
+    pub fn set_company_prefix(org: sr25519::Public, prefix: &[u8]) {
+        COMPANY_PREFIXES.with(|prefixes| prefixes.borrow_mut().insert(org, prefix.to_vec()));
+    }
+}
+
+impl Get<bool> for MockGs1 {
+    fn get() -> bool {
+        ENFORCE_GS1.with(|v| *v.borrow())
+    }
+}
 
-impl<T: Trait> EnsureOrigin<T::Origin> for MockOrigin<T> {
-    type Success = T::AccountId;
-    fn try_origin(o: T::Origin) -> Result<Self::Success, T::Origin> {
-        o.into().and_then(|o| match o {
-            RawOrigin::Signed(ref who) => Ok(who.clone()),
-            r => Err(T::Origin::from(r)),
+impl CompanyPrefixProvider<sr25519::Public> for MockGs1 {
+    fn company_prefix(org: &sr25519::Public) -> Option<Vec<u8>> {
+        COMPANY_PREFIXES.with(|prefixes| prefixes.borrow().get(org).cloned())
+    }
+}
+
+// Stubs the consortium organizations & their members (or delegates).
+pub struct MockOrganizations;
+
+impl MockOrganizations {
+    pub fn add_member(org: sr25519::Public, account: sr25519::Public) {
+        ORGANIZATIONS.with(|orgs| orgs.borrow_mut().entry(org).or_default().push(account));
+    }
+}
+
+impl OrganizationProvider<sr25519::Public> for MockOrganizations {
+    fn is_organization(org: &sr25519::Public) -> bool {
+        ORGANIZATIONS.with(|orgs| orgs.borrow().contains_key(org))
+    }
+
+    fn is_member_or_delegate(org: &sr25519::Public, account: &sr25519::Public) -> bool {
+        ORGANIZATIONS.with(|orgs| {
+            orgs.borrow()
+                .get(org)
+                .map_or(false, |members| members.contains(account))
         })
     }
 }
@@ -98,14 +168,22 @@ impl<T: Trait> EnsureOrigin<T::Origin> for MockOrigin<T> {
 // This function basically just builds a genesis storage key/value store according to
 // our desired mockup.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    let storage = frame_system::GenesisConfig::default()
+    let mut storage = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
         .unwrap();
+    product_registry::GenesisConfig::default()
+        .assimilate_storage::<Test>(&mut storage)
+        .unwrap();
 
     let mut ext = sp_io::TestExternalities::from(storage);
     // Events are not emitted on block 0 -> advance to block 1.
     // Any dispatchable calls made during genesis block will have no events emitted.
     ext.execute_with(|| System::set_block_number(1));
+    // Test organizations, Alice acting on behalf of both.
+    MockOrganizations::add_member(account_key("Northwind"), account_key("Alice"));
+    MockOrganizations::add_member(account_key("Contoso"), account_key("Alice"));
+    // Test certification body, Bob inspecting on its behalf.
+    MockOrganizations::add_member(account_key("Ecocert"), account_key("Bob"));
     ext
 }
 
@@ -2,7 +2,11 @@
 
 use super::*;
 use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok, dispatch};
+use frame_support::{
+    assert_noop, assert_ok, dispatch,
+    storage::{StorageDoubleMap, StorageMap},
+    traits::OnRuntimeUpgrade,
+};
 
 pub fn store_test_product<T: Trait>(id: ProductId, owner: T::AccountId, registered: T::Moment) {
     Products::<T>::insert(
@@ -12,12 +16,14 @@ pub fn store_test_product<T: Trait>(id: ProductId, owner: T::AccountId, register
             owner,
             registered,
             props: None,
+            status: ProductStatus::Active,
         },
     );
 }
 
 const TEST_PRODUCT_ID: &str = "00012345600012";
 const TEST_ORGANIZATION: &str = "Northwind";
+const TEST_OTHER_ORGANIZATION: &str = "Contoso";
 const TEST_SENDER: &str = "Alice";
 const LONG_VALUE : &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Donec aliquam ut tortor nec congue. Pellente";
 
@@ -35,6 +41,7 @@ fn create_product_without_props() {
             id.clone(),
             owner.clone(),
             None,
+            None,
         );
 
         assert_ok!(result);
@@ -45,7 +52,8 @@ fn create_product_without_props() {
                 id: id.clone(),
                 owner: owner,
                 registered: now,
-                props: None
+                props: None,
+                status: ProductStatus::Active,
             })
         );
 
@@ -77,10 +85,11 @@ fn create_product_with_valid_props() {
             id.clone(),
             owner.clone(),
             Some(vec![
-                ProductProperty::new(b"prop1", b"val1"),
-                ProductProperty::new(b"prop2", b"val2"),
-                ProductProperty::new(b"prop3", b"val3"),
+                ProductProperty::text(b"prop1", b"val1"),
+                ProductProperty::text(b"prop2", b"val2"),
+                ProductProperty::text(b"prop3", b"val3"),
             ]),
+            None,
         );
 
         assert_ok!(result);
@@ -92,10 +101,11 @@ fn create_product_with_valid_props() {
                 owner: owner,
                 registered: now,
                 props: Some(vec![
-                    ProductProperty::new(b"prop1", b"val1"),
-                    ProductProperty::new(b"prop2", b"val2"),
-                    ProductProperty::new(b"prop3", b"val3"),
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2"),
+                    ProductProperty::text(b"prop3", b"val3"),
                 ]),
+                status: ProductStatus::Active,
             })
         );
 
@@ -121,6 +131,7 @@ fn create_product_with_invalid_sender() {
                 Origin::none(),
                 vec!(),
                 account_key(TEST_ORGANIZATION),
+                None,
                 None
             ),
             dispatch::DispatchError::BadOrigin
@@ -136,6 +147,7 @@ fn create_product_with_missing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 vec!(),
                 account_key(TEST_ORGANIZATION),
+                None,
                 None
             ),
             Error::<Test>::ProductIdMissing
@@ -151,6 +163,7 @@ fn create_product_with_long_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 LONG_VALUE.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
+                None,
                 None
             ),
             Error::<Test>::ProductIdTooLong
@@ -175,6 +188,7 @@ fn create_product_with_existing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 existing_product,
                 account_key(TEST_ORGANIZATION),
+                None,
                 None
             ),
             Error::<Test>::ProductIdExists
@@ -182,6 +196,38 @@ fn create_product_with_existing_id() {
     })
 }
 
+#[test]
+fn create_product_with_unknown_organization() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                account_key(TEST_SENDER),
+                None,
+                None
+            ),
+            Error::<Test>::OwnerNotOrganization
+        );
+    })
+}
+
+#[test]
+fn create_product_with_sender_not_member() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_OTHER_ORGANIZATION)),
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                None,
+                None
+            ),
+            Error::<Test>::SenderNotMemberOrDelegate
+        );
+    })
+}
+
 #[test]
 fn create_product_with_too_many_props() {
     new_test_ext().execute_with(|| {
@@ -191,11 +237,12 @@ fn create_product_with_too_many_props() {
                 TEST_PRODUCT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
                 Some(vec![
-                    ProductProperty::new(b"prop1", b"val1"),
-                    ProductProperty::new(b"prop2", b"val2"),
-                    ProductProperty::new(b"prop3", b"val3"),
-                    ProductProperty::new(b"prop4", b"val4")
-                ])
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2"),
+                    ProductProperty::text(b"prop3", b"val3"),
+                    ProductProperty::text(b"prop4", b"val4")
+                ]),
+                None
             ),
             Error::<Test>::ProductTooManyProps
         );
@@ -211,10 +258,11 @@ fn create_product_with_invalid_prop_name() {
                 TEST_PRODUCT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
                 Some(vec![
-                    ProductProperty::new(b"prop1", b"val1"),
-                    ProductProperty::new(b"prop2", b"val2"),
-                    ProductProperty::new(&LONG_VALUE.as_bytes().to_owned(), b"val3"),
-                ])
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2"),
+                    ProductProperty::text(&LONG_VALUE.as_bytes().to_owned(), b"val3"),
+                ]),
+                None
             ),
             Error::<Test>::ProductInvalidPropName
         );
@@ -230,12 +278,1709 @@ fn create_product_with_invalid_prop_value() {
                 TEST_PRODUCT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
                 Some(vec![
-                    ProductProperty::new(b"prop1", b"val1"),
-                    ProductProperty::new(b"prop2", b"val2"),
-                    ProductProperty::new(b"prop3", &LONG_VALUE.as_bytes().to_owned()),
-                ])
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2"),
+                    ProductProperty::text(b"prop3", &LONG_VALUE.as_bytes().to_owned()),
+                ]),
+                None
             ),
             Error::<Test>::ProductInvalidPropValue
         );
     })
 }
+
+#[test]
+fn transfer_product() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let new_owner = account_key(TEST_OTHER_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(owner),
+            id.clone(),
+            owner.clone(),
+            None,
+            None,
+        ));
+
+        let result =
+            ProductRegistry::transfer_product(Origin::signed(owner), id.clone(), new_owner.clone());
+
+        assert_ok!(result);
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id),
+            Some(Product {
+                id: id.clone(),
+                owner: new_owner,
+                registered: now,
+                props: None,
+                status: ProductStatus::Active,
+            })
+        );
+
+        assert_eq!(<ProductsOfOrganization<Test>>::get(owner), Vec::<ProductId>::new());
+        assert_eq!(
+            <ProductsOfOrganization<Test>>::get(new_owner),
+            vec![id.clone()]
+        );
+
+        assert_eq!(ProductRegistry::owner_of(&id), Some(new_owner));
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::ProductTransferred(
+                owner,
+                id.clone(),
+                owner,
+                new_owner
+            ))));
+    });
+}
+
+#[test]
+fn transfer_product_against_schema() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let new_owner = account_key(TEST_OTHER_ORGANIZATION);
+        let schema_id = TEST_SCHEMA_ID.as_bytes().to_owned();
+
+        assert_ok!(ProductRegistry::register_schema(
+            Origin::signed(owner),
+            owner,
+            schema_id.clone(),
+            test_schema()
+        ));
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(owner),
+            id.clone(),
+            owner,
+            Some(vec![ProductProperty::text(b"desc", b"burger")]),
+            Some(schema_id),
+        ));
+
+        assert_ok!(ProductRegistry::transfer_product(
+            Origin::signed(owner),
+            id.clone(),
+            new_owner
+        ));
+
+        // The schema of the previous owner no longer applies
+        assert_eq!(ProductRegistry::schema_of(&id), None);
+        assert_ok!(ProductRegistry::update_product_props(
+            Origin::signed(new_owner),
+            id,
+            ProductPropsUpdate::Replace(None)
+        ));
+    });
+}
+
+#[test]
+fn transfer_product_with_unknown_id() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+
+        assert_noop!(
+            ProductRegistry::transfer_product(
+                Origin::signed(owner),
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                account_key(TEST_OTHER_ORGANIZATION)
+            ),
+            Error::<Test>::ProductIdUnknown
+        );
+    })
+}
+
+#[test]
+fn transfer_product_not_owned_by_sender() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+
+        store_test_product::<Test>(id.clone(), account_key(TEST_ORGANIZATION), 42);
+
+        assert_noop!(
+            ProductRegistry::transfer_product(
+                Origin::signed(account_key(TEST_OTHER_ORGANIZATION)),
+                id,
+                account_key(TEST_OTHER_ORGANIZATION)
+            ),
+            Error::<Test>::ProductNotOwnedBySender
+        );
+    })
+}
+
+#[test]
+fn transfer_product_to_current_owner() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(id.clone(), owner, 42);
+
+        assert_noop!(
+            ProductRegistry::transfer_product(Origin::signed(owner), id, owner),
+            Error::<Test>::ProductAlreadyOwned
+        );
+    })
+}
+
+#[test]
+fn transfer_product_to_unknown_organization() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(id.clone(), owner, 42);
+
+        assert_noop!(
+            ProductRegistry::transfer_product(Origin::signed(owner), id, account_key(TEST_SENDER)),
+            Error::<Test>::OwnerNotOrganization
+        );
+    })
+}
+
+#[test]
+fn update_product_props_with_patch() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(owner),
+            id.clone(),
+            owner.clone(),
+            Some(vec![
+                ProductProperty::text(b"prop1", b"val1"),
+                ProductProperty::text(b"prop2", b"val2"),
+            ]),
+            None,
+        ));
+
+        System::set_block_number(2);
+        Timestamp::set_timestamp(now + 6000);
+
+        let result = ProductRegistry::update_product_props(
+            Origin::signed(owner),
+            id.clone(),
+            ProductPropsUpdate::Patch(vec![
+                ProductProperty::text(b"prop2", b"val2b"),
+                ProductProperty::text(b"prop3", b"val3"),
+            ]),
+        );
+
+        assert_ok!(result);
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id),
+            Some(Product {
+                id: id.clone(),
+                owner: owner,
+                registered: now,
+                props: Some(vec![
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2b"),
+                    ProductProperty::text(b"prop3", b"val3"),
+                ]),
+                status: ProductStatus::Active,
+            })
+        );
+
+        assert_eq!(
+            ProductRegistry::product_revisions(&id),
+            vec![ProductRevision {
+                props: Some(vec![
+                    ProductProperty::text(b"prop1", b"val1"),
+                    ProductProperty::text(b"prop2", b"val2"),
+                ]),
+                changed_by: owner,
+                block: 2,
+                timestamp: now + 6000,
+            }]
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::ProductUpdated(owner, id.clone()))));
+    });
+}
+
+#[test]
+fn update_product_props_with_replace() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        store_test_product::<Test>(id.clone(), owner, now);
+
+        assert_ok!(ProductRegistry::update_product_props(
+            Origin::signed(owner),
+            id.clone(),
+            ProductPropsUpdate::Replace(Some(vec![ProductProperty::text(b"prop1", b"val1")])),
+        ));
+
+        assert_ok!(ProductRegistry::update_product_props(
+            Origin::signed(owner),
+            id.clone(),
+            ProductPropsUpdate::Replace(None),
+        ));
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id),
+            Some(Product {
+                id: id.clone(),
+                owner: owner,
+                registered: now,
+                props: None,
+                status: ProductStatus::Active,
+            })
+        );
+
+        let revisions = ProductRegistry::product_revisions(&id);
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].props(), &None);
+        assert_eq!(
+            revisions[1].props(),
+            &Some(vec![ProductProperty::text(b"prop1", b"val1")])
+        );
+    });
+}
+
+#[test]
+fn update_product_props_not_owned_by_sender() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+
+        store_test_product::<Test>(id.clone(), account_key(TEST_ORGANIZATION), 42);
+
+        assert_noop!(
+            ProductRegistry::update_product_props(
+                Origin::signed(account_key(TEST_OTHER_ORGANIZATION)),
+                id,
+                ProductPropsUpdate::Replace(None),
+            ),
+            Error::<Test>::ProductNotOwnedBySender
+        );
+    })
+}
+
+#[test]
+fn update_product_props_with_too_many_props() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(owner),
+            id.clone(),
+            owner.clone(),
+            Some(vec![
+                ProductProperty::text(b"prop1", b"val1"),
+                ProductProperty::text(b"prop2", b"val2"),
+                ProductProperty::text(b"prop3", b"val3"),
+            ]),
+            None,
+        ));
+
+        assert_noop!(
+            ProductRegistry::update_product_props(
+                Origin::signed(owner),
+                id,
+                ProductPropsUpdate::Patch(vec![ProductProperty::text(b"prop4", b"val4")]),
+            ),
+            Error::<Test>::ProductTooManyProps
+        );
+    })
+}
+
+#[test]
+fn discontinue_product() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(id.clone(), owner, 42);
+
+        assert_ok!(ProductRegistry::discontinue_product(
+            Origin::signed(owner),
+            id.clone()
+        ));
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id).map(|p| p.status),
+            Some(ProductStatus::Discontinued)
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::ProductDiscontinued(owner, id.clone()))));
+
+        // A discontinued product cannot be discontinued again
+        assert_noop!(
+            ProductRegistry::discontinue_product(Origin::signed(owner), id),
+            Error::<Test>::ProductNotActive
+        );
+    });
+}
+
+#[test]
+fn recall_product() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(id.clone(), owner, 42);
+
+        assert_ok!(ProductRegistry::discontinue_product(
+            Origin::signed(owner),
+            id.clone()
+        ));
+        assert_ok!(ProductRegistry::recall_product(
+            Origin::signed(owner),
+            id.clone()
+        ));
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id).map(|p| p.status),
+            Some(ProductStatus::Recalled)
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::ProductRecalled(owner, id.clone()))));
+
+        assert_noop!(
+            ProductRegistry::recall_product(Origin::signed(owner), id.clone()),
+            Error::<Test>::ProductAlreadyRecalled
+        );
+        assert_noop!(
+            ProductRegistry::discontinue_product(Origin::signed(owner), id),
+            Error::<Test>::ProductNotActive
+        );
+    });
+}
+
+#[test]
+fn recall_product_not_owned_by_sender() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+
+        store_test_product::<Test>(id.clone(), account_key(TEST_ORGANIZATION), 42);
+
+        assert_noop!(
+            ProductRegistry::recall_product(Origin::signed(account_key(TEST_OTHER_ORGANIZATION)), id),
+            Error::<Test>::ProductNotOwnedBySender
+        );
+    })
+}
+
+#[test]
+fn manage_product_as_member() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let component = b"component".to_vec();
+        let lot_id = TEST_LOT_ID.as_bytes().to_owned();
+        let org = account_key(TEST_ORGANIZATION);
+        // A member, not the organization account, signs all the calls
+        let member = Origin::signed(account_key(TEST_SENDER));
+
+        assert_ok!(ProductRegistry::register_product(member.clone(), id.clone(), org, None, None));
+        assert_ok!(ProductRegistry::register_product(
+            member.clone(),
+            component.clone(),
+            org,
+            None,
+            None
+        ));
+        assert_ok!(ProductRegistry::update_product_props(
+            member.clone(),
+            id.clone(),
+            ProductPropsUpdate::Patch(vec![ProductProperty::text(b"desc", b"Pizza")])
+        ));
+        assert_ok!(ProductRegistry::register_lot(
+            member.clone(),
+            id.clone(),
+            lot_id.clone(),
+            40,
+            None,
+            10,
+            None
+        ));
+        assert_ok!(ProductRegistry::update_lot(
+            member.clone(),
+            id.clone(),
+            lot_id,
+            None,
+            20,
+            None
+        ));
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            member.clone(),
+            id.clone(),
+            vec![BomComponent::new(&component, 1)]
+        ));
+        assert_ok!(ProductRegistry::discontinue_product(member.clone(), id.clone()));
+        assert_ok!(ProductRegistry::recall_product(member.clone(), id.clone()));
+        assert_ok!(ProductRegistry::transfer_product(
+            member,
+            id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+
+        assert_eq!(
+            ProductRegistry::owner_of(&id),
+            Some(account_key(TEST_OTHER_ORGANIZATION))
+        );
+        assert_eq!(
+            ProductRegistry::product_by_id(&id).map(|p| p.status),
+            Some(ProductStatus::Recalled)
+        );
+    })
+}
+
+#[test]
+fn migrate_products_from_v1() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let props = Some(vec![(b"prop1".to_vec(), b"val1".to_vec())]);
+
+        // Store a product w/o lifecycle status & with untyped property values
+        frame_support::storage::unhashed::put(
+            &Products::<Test>::hashed_key_for(&id),
+            &(id.clone(), owner, props, 42u64),
+        );
+        StorageVersion::put(Releases::V1_0_0);
+
+        ProductRegistry::on_runtime_upgrade();
+
+        assert_eq!(StorageVersion::get(), Releases::V3_0_0);
+        assert_eq!(
+            ProductRegistry::product_by_id(&id),
+            Some(Product {
+                id: id.clone(),
+                owner: owner,
+                registered: 42,
+                props: Some(vec![ProductProperty::text(b"prop1", b"val1")]),
+                status: ProductStatus::Active,
+            })
+        );
+    })
+}
+
+#[test]
+fn migrate_products_from_v2() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let lot_id = TEST_LOT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let props = Some(vec![(b"prop1".to_vec(), b"val1".to_vec())]);
+
+        // Store a product & lot with untyped property values
+        frame_support::storage::unhashed::put(
+            &Products::<Test>::hashed_key_for(&id),
+            &(id.clone(), owner, props.clone(), 42u64, ProductStatus::Recalled),
+        );
+        frame_support::storage::unhashed::put(
+            &Lots::<Test>::hashed_key_for(&id, &lot_id),
+            &(lot_id.clone(), id.clone(), 40u64, None::<u64>, 10u64, props, 42u64),
+        );
+        StorageVersion::put(Releases::V2_0_0);
+
+        ProductRegistry::on_runtime_upgrade();
+
+        assert_eq!(StorageVersion::get(), Releases::V3_0_0);
+        assert_eq!(
+            ProductRegistry::product_by_id(&id),
+            Some(Product {
+                id: id.clone(),
+                owner: owner,
+                registered: 42,
+                props: Some(vec![ProductProperty::text(b"prop1", b"val1")]),
+                status: ProductStatus::Recalled,
+            })
+        );
+        assert_eq!(
+            ProductRegistry::lot_by_id(&id, &lot_id),
+            Some(Lot {
+                id: lot_id.clone(),
+                product_id: id.clone(),
+                produced: 40,
+                expires: None,
+                quantity: 10,
+                props: Some(vec![ProductProperty::text(b"prop1", b"val1")]),
+                registered: 42,
+            })
+        );
+    })
+}
+
+#[test]
+fn gtin_check_digit() {
+    assert!(gs1::is_valid_format(b"00012345600012"));
+    assert!(gs1::is_valid_format(b"4012345000016"));
+    assert!(!gs1::is_valid_format(b"40123450001"));
+    assert!(!gs1::is_valid_format(b"401234500001A"));
+
+    assert_eq!(gs1::check_digit(b"00012345600012"), 2);
+    assert!(gs1::is_valid_check_digit(b"4012345000016"));
+    assert!(!gs1::is_valid_check_digit(b"4012345000017"));
+
+    assert_eq!(gs1::to_gtin13(b"00012345600012"), b"0012345600012".to_vec());
+    assert_eq!(gs1::to_gtin13(b"012345000058"), b"0012345000058".to_vec());
+
+    assert!(gs1::is_valid_check_digit(b"96385074"));
+    assert!(!gs1::has_company_prefix(b"96385074"));
+    assert!(gs1::has_company_prefix(b"012345000058"));
+}
+
+#[test]
+fn create_product_with_gtin8() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        MockGs1::enforce(true);
+        MockGs1::set_company_prefix(owner, b"0000096");
+
+        // GTIN-8 do not contain a company prefix, even once zero-padded
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                b"96385074".to_vec(),
+                owner,
+                None,
+                None
+            ),
+            Error::<Test>::ProductIdWithoutCompanyPrefix
+        );
+    })
+}
+
+#[test]
+fn create_product_with_valid_gtin() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        MockGs1::enforce(true);
+        MockGs1::set_company_prefix(owner, b"0012345");
+
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(account_key(TEST_SENDER)),
+            TEST_PRODUCT_ID.as_bytes().to_owned(),
+            owner,
+            None,
+            None
+        ));
+    })
+}
+
+#[test]
+fn create_product_with_invalid_gtin() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        MockGs1::enforce(true);
+        MockGs1::set_company_prefix(owner, b"0012345");
+
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                b"0001234560001".to_vec(),
+                owner,
+                None,
+                None
+            ),
+            Error::<Test>::ProductIdInvalidGtin
+        );
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                b"00012345600013".to_vec(),
+                owner,
+                None,
+                None
+            ),
+            Error::<Test>::ProductIdInvalidCheckDigit
+        );
+    })
+}
+
+#[test]
+fn create_product_with_gtin_of_other_company() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        MockGs1::enforce(true);
+
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                owner,
+                None,
+                None
+            ),
+            Error::<Test>::OrganizationMissingCompanyPrefix
+        );
+
+        MockGs1::set_company_prefix(owner, b"4012345");
+
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_PRODUCT_ID.as_bytes().to_owned(),
+                owner,
+                None,
+                None
+            ),
+            Error::<Test>::ProductIdCompanyPrefixMismatch
+        );
+    })
+}
+
+const TEST_LOT_ID: &str = "L20200901";
+
+#[test]
+fn register_lot() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let lot_id = TEST_LOT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        store_test_product::<Test>(product_id.clone(), owner, now);
+
+        let result = ProductRegistry::register_lot(
+            Origin::signed(owner),
+            product_id.clone(),
+            lot_id.clone(),
+            40,
+            Some(1000),
+            500,
+            Some(vec![ProductProperty::text(b"origin", b"Sicily")]),
+        );
+
+        assert_ok!(result);
+
+        assert_eq!(
+            ProductRegistry::lot_by_id(&product_id, &lot_id),
+            Some(Lot {
+                id: lot_id.clone(),
+                product_id: product_id.clone(),
+                produced: 40,
+                expires: Some(1000),
+                quantity: 500,
+                props: Some(vec![ProductProperty::text(b"origin", b"Sicily")]),
+                registered: now,
+            })
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::LotRegistered(
+                owner,
+                product_id.clone(),
+                lot_id.clone()
+            ))));
+
+        // Lot IDs are unique per product
+        assert_noop!(
+            ProductRegistry::register_lot(
+                Origin::signed(owner),
+                product_id,
+                lot_id,
+                40,
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::LotIdExists
+        );
+    });
+}
+
+#[test]
+fn register_lot_with_invalid_lot_id() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(product_id.clone(), owner, 42);
+
+        assert_noop!(
+            ProductRegistry::register_lot(
+                Origin::signed(owner),
+                product_id.clone(),
+                vec![],
+                40,
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::LotIdMissing
+        );
+        assert_noop!(
+            ProductRegistry::register_lot(
+                Origin::signed(owner),
+                product_id,
+                LONG_VALUE.as_bytes().to_owned(),
+                40,
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::LotIdTooLong
+        );
+    });
+}
+
+#[test]
+fn register_lot_of_inactive_product() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(product_id.clone(), owner, 42);
+        assert_ok!(ProductRegistry::discontinue_product(
+            Origin::signed(owner),
+            product_id.clone()
+        ));
+
+        assert_noop!(
+            ProductRegistry::register_lot(
+                Origin::signed(owner),
+                product_id,
+                TEST_LOT_ID.as_bytes().to_owned(),
+                40,
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::ProductNotActive
+        );
+    });
+}
+
+#[test]
+fn update_lot() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let lot_id = TEST_LOT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        store_test_product::<Test>(product_id.clone(), owner, now);
+        assert_ok!(ProductRegistry::register_lot(
+            Origin::signed(owner),
+            product_id.clone(),
+            lot_id.clone(),
+            40,
+            Some(1000),
+            500,
+            None,
+        ));
+
+        assert_ok!(ProductRegistry::update_lot(
+            Origin::signed(owner),
+            product_id.clone(),
+            lot_id.clone(),
+            Some(2000),
+            450,
+            Some(vec![ProductProperty::text(b"origin", b"Sicily")]),
+        ));
+
+        assert_eq!(
+            ProductRegistry::lot_by_id(&product_id, &lot_id),
+            Some(Lot {
+                id: lot_id.clone(),
+                product_id: product_id.clone(),
+                produced: 40,
+                expires: Some(2000),
+                quantity: 450,
+                props: Some(vec![ProductProperty::text(b"origin", b"Sicily")]),
+                registered: now,
+            })
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::LotUpdated(
+                owner,
+                product_id.clone(),
+                lot_id.clone()
+            ))));
+    });
+}
+
+#[test]
+fn update_unknown_lot() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(product_id.clone(), owner, 42);
+
+        assert_noop!(
+            ProductRegistry::update_lot(
+                Origin::signed(owner),
+                product_id,
+                TEST_LOT_ID.as_bytes().to_owned(),
+                None,
+                1,
+                None
+            ),
+            Error::<Test>::LotIdUnknown
+        );
+    });
+}
+
+#[test]
+fn set_bill_of_materials() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let pizza = b"pizza".to_vec();
+        let dough = b"dough".to_vec();
+        let flour = b"flour".to_vec();
+        let cheese = b"cheese".to_vec();
+
+        for id in &[&pizza, &dough, &flour, &cheese] {
+            store_test_product::<Test>(id.to_vec(), owner, 42);
+        }
+
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            Origin::signed(owner),
+            dough.clone(),
+            vec![BomComponent::new(&flour, 2)]
+        ));
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            Origin::signed(owner),
+            pizza.clone(),
+            vec![BomComponent::new(&dough, 1), BomComponent::new(&cheese, 3)]
+        ));
+
+        assert_eq!(
+            ProductRegistry::components_of(&pizza),
+            vec![BomComponent::new(&dough, 1), BomComponent::new(&cheese, 3)]
+        );
+        assert_eq!(ProductRegistry::used_in(&flour), vec![dough.clone()]);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::BillOfMaterialsSet(owner, pizza.clone()))));
+
+        // Walks are weighed for the worst case, BomMaxComponents^BomMaxDepth products
+        assert_eq!(ProductRegistry::bom_walk_max_reads(), 2 * 5u64.pow(3));
+
+        // Walk the graph downward & upward
+        assert_eq!(
+            ProductRegistry::components_tree(&pizza, 10),
+            vec![dough.clone(), cheese.clone(), flour.clone()]
+        );
+        assert_eq!(
+            ProductRegistry::components_tree(&pizza, 1),
+            vec![dough.clone(), cheese.clone()]
+        );
+        assert_eq!(
+            ProductRegistry::products_containing(&flour, 10),
+            vec![dough.clone(), pizza.clone()]
+        );
+
+        // Replacing the bill of materials unlinks previous components
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            Origin::signed(owner),
+            pizza.clone(),
+            vec![BomComponent::new(&dough, 1)]
+        ));
+        assert_eq!(ProductRegistry::used_in(&cheese), Vec::<ProductId>::new());
+        assert_eq!(ProductRegistry::products_containing(&flour, 10), vec![dough, pizza]);
+    });
+}
+
+#[test]
+fn set_bill_of_materials_with_cycle() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let a = b"a".to_vec();
+        let b = b"b".to_vec();
+        let c = b"c".to_vec();
+
+        for id in &[&a, &b, &c] {
+            store_test_product::<Test>(id.to_vec(), owner, 42);
+        }
+
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(owner),
+                a.clone(),
+                vec![BomComponent::new(&a, 1)]
+            ),
+            Error::<Test>::BomHasCycle
+        );
+
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            Origin::signed(owner),
+            a.clone(),
+            vec![BomComponent::new(&b, 1)]
+        ));
+        assert_ok!(ProductRegistry::set_bill_of_materials(
+            Origin::signed(owner),
+            b.clone(),
+            vec![BomComponent::new(&c, 1)]
+        ));
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(owner),
+                c,
+                vec![BomComponent::new(&a, 1)]
+            ),
+            Error::<Test>::BomHasCycle
+        );
+    });
+}
+
+#[test]
+fn set_bill_of_materials_with_invalid_components() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let a = b"a".to_vec();
+        let b = b"b".to_vec();
+
+        store_test_product::<Test>(a.clone(), owner, 42);
+        store_test_product::<Test>(b.clone(), owner, 42);
+
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(owner),
+                a.clone(),
+                vec![BomComponent::new(b"unknown", 1)]
+            ),
+            Error::<Test>::BomComponentUnknown
+        );
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(owner),
+                a.clone(),
+                vec![BomComponent::new(&b, 0)]
+            ),
+            Error::<Test>::BomComponentInvalidQuantity
+        );
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(owner),
+                a.clone(),
+                vec![BomComponent::new(&b, 1), BomComponent::new(&b, 2)]
+            ),
+            Error::<Test>::BomComponentDuplicate
+        );
+        assert_noop!(
+            ProductRegistry::set_bill_of_materials(
+                Origin::signed(account_key(TEST_OTHER_ORGANIZATION)),
+                a,
+                vec![BomComponent::new(&b, 1)]
+            ),
+            Error::<Test>::ProductNotOwnedBySender
+        );
+    });
+}
+
+#[test]
+fn products_of_organization_page() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let ids: Vec<ProductId> = vec![b"p1".to_vec(), b"p2".to_vec(), b"p3".to_vec()];
+        for id in ids.iter() {
+            store_test_product::<Test>(id.clone(), owner, 42);
+            ProductsOfOrganization::<Test>::append(owner, id);
+        }
+
+        let page_ids = |page: Vec<Product<_, _>>| -> Vec<ProductId> {
+            page.iter().map(|p| p.id().to_vec()).collect()
+        };
+
+        let (products, next) = ProductRegistry::products_of_organization_page(&owner, None, 2);
+        assert_eq!(page_ids(products), ids[..2].to_vec());
+        assert_eq!(next, Some(ids[1].clone()));
+
+        let (products, next) = ProductRegistry::products_of_organization_page(&owner, next, 2);
+        assert_eq!(page_ids(products), ids[2..].to_vec());
+        assert_eq!(next, None);
+
+        // Unknown cursor
+        let (products, next) =
+            ProductRegistry::products_of_organization_page(&owner, Some(b"p9".to_vec()), 2);
+        assert!(products.is_empty());
+        assert_eq!(next, None);
+    });
+}
+
+#[test]
+fn create_product_with_typed_props() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let props = vec![
+            ProductProperty::new(b"count", PropValue::Integer(12)),
+            ProductProperty::new(
+                b"weight",
+                PropValue::UnitQuantity(UnitQuantity {
+                    value: PropDecimal::from_num(12.5),
+                    unit: b"kg".to_vec(),
+                }),
+            ),
+            ProductProperty::new(b"organic", PropValue::Boolean(true)),
+        ];
+
+        assert_ok!(ProductRegistry::register_product(
+            Origin::signed(sender),
+            id.clone(),
+            owner,
+            Some(props.clone()),
+            None
+        ));
+
+        assert_eq!(
+            ProductRegistry::product_by_id(&id).and_then(|p| p.props().clone()),
+            Some(props)
+        );
+    });
+}
+
+#[test]
+fn create_product_with_invalid_unit() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        for unit in &[&b""[..], &b"k g"[..]] {
+            assert_noop!(
+                ProductRegistry::register_product(
+                    Origin::signed(sender),
+                    id.clone(),
+                    owner,
+                    Some(vec![ProductProperty::new(
+                        b"weight",
+                        PropValue::UnitQuantity(UnitQuantity {
+                            value: PropDecimal::from_num(12.5),
+                            unit: unit.to_vec(),
+                        }),
+                    )]),
+                    None
+                ),
+                Error::<Test>::ProductInvalidPropUnit
+            );
+        }
+    });
+}
+
+const TEST_SCHEMA_ID: &str = "food";
+
+fn test_schema() -> Vec<PropSchema> {
+    vec![
+        PropSchema::new(b"desc", PropType::Text, true, Some(10)),
+        PropSchema::new(b"organic", PropType::Boolean, false, None),
+    ]
+}
+
+#[test]
+fn register_schema() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let schema_id = TEST_SCHEMA_ID.as_bytes().to_owned();
+
+        assert_ok!(ProductRegistry::register_schema(
+            Origin::signed(sender),
+            owner,
+            schema_id.clone(),
+            test_schema()
+        ));
+
+        // Schema is registered for the organization
+        assert_eq!(
+            ProductRegistry::schema_by_id(owner, &schema_id),
+            Some(test_schema())
+        );
+        assert_eq!(ProductRegistry::schema_by_id(sender, &schema_id), None);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::SchemaRegistered(owner, schema_id.clone()))));
+
+        // Schema IDs are unique per organization
+        assert_noop!(
+            ProductRegistry::register_schema(Origin::signed(owner), owner, schema_id.clone(), vec![]),
+            Error::<Test>::SchemaIdExists
+        );
+        assert_ok!(ProductRegistry::register_schema(
+            Origin::signed(sender),
+            account_key(TEST_OTHER_ORGANIZATION),
+            schema_id,
+            vec![]
+        ));
+    });
+}
+
+#[test]
+fn register_schema_of_other_organization() {
+    new_test_ext().execute_with(|| {
+        let schema_id = TEST_SCHEMA_ID.as_bytes().to_owned();
+
+        // Sender must be a member or delegate of the organization
+        assert_noop!(
+            ProductRegistry::register_schema(
+                Origin::signed(account_key(TEST_OTHER_ORGANIZATION)),
+                account_key(TEST_ORGANIZATION),
+                schema_id.clone(),
+                test_schema()
+            ),
+            Error::<Test>::SenderNotMemberOrDelegate
+        );
+        assert_noop!(
+            ProductRegistry::register_schema(
+                Origin::signed(account_key(TEST_SENDER)),
+                account_key(TEST_SENDER),
+                schema_id,
+                test_schema()
+            ),
+            Error::<Test>::OwnerNotOrganization
+        );
+    });
+}
+
+#[test]
+fn register_invalid_schema() {
+    new_test_ext().execute_with(|| {
+        let sender = Origin::signed(account_key(TEST_SENDER));
+        let owner = account_key(TEST_ORGANIZATION);
+        let schema_id = TEST_SCHEMA_ID.as_bytes().to_owned();
+
+        assert_noop!(
+            ProductRegistry::register_schema(sender.clone(), owner, vec![], test_schema()),
+            Error::<Test>::SchemaIdMissing
+        );
+        assert_noop!(
+            ProductRegistry::register_schema(sender.clone(), owner, vec![b'x'; 11], test_schema()),
+            Error::<Test>::SchemaIdTooLong
+        );
+        assert_noop!(
+            ProductRegistry::register_schema(
+                sender.clone(),
+                owner,
+                schema_id.clone(),
+                vec![
+                    PropSchema::new(b"desc", PropType::Text, true, None),
+                    PropSchema::new(b"desc", PropType::Integer, false, None),
+                ]
+            ),
+            Error::<Test>::SchemaDuplicateProp
+        );
+        assert_noop!(
+            ProductRegistry::register_schema(
+                sender,
+                owner,
+                schema_id,
+                vec![PropSchema::new(b"desc", PropType::Text, true, Some(1000))]
+            ),
+            Error::<Test>::SchemaInvalidProp
+        );
+    });
+}
+
+#[test]
+fn create_product_against_schema() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let schema_id = TEST_SCHEMA_ID.as_bytes().to_owned();
+
+        assert_ok!(ProductRegistry::register_schema(
+            Origin::signed(owner),
+            owner,
+            schema_id.clone(),
+            test_schema()
+        ));
+
+        let register = |props: Vec<ProductProperty>| {
+            ProductRegistry::register_product(
+                Origin::signed(sender),
+                id.clone(),
+                owner,
+                Some(props),
+                Some(schema_id.clone()),
+            )
+        };
+
+        assert_noop!(
+            register(vec![ProductProperty::new(b"organic", PropValue::Boolean(true))]),
+            Error::<Test>::ProductMissingRequiredProp
+        );
+        assert_noop!(
+            register(vec![ProductProperty::text(b"Desc", b"burger")]),
+            Error::<Test>::ProductUnknownProp
+        );
+        assert_noop!(
+            register(vec![ProductProperty::new(b"desc", PropValue::Integer(1))]),
+            Error::<Test>::ProductInvalidPropType
+        );
+        assert_noop!(
+            register(vec![ProductProperty::text(b"desc", b"veggie burger")]),
+            Error::<Test>::ProductInvalidPropValue
+        );
+        assert_noop!(
+            ProductRegistry::register_product(
+                Origin::signed(sender),
+                id.clone(),
+                owner,
+                None,
+                Some(b"unknown".to_vec()),
+            ),
+            Error::<Test>::SchemaIdUnknown
+        );
+
+        assert_ok!(register(vec![ProductProperty::text(b"desc", b"burger")]));
+        assert_eq!(ProductRegistry::schema_of(&id), Some((owner, schema_id)));
+
+        // Updates are validated against the schema too
+        assert_noop!(
+            ProductRegistry::update_product_props(
+                Origin::signed(owner),
+                id.clone(),
+                ProductPropsUpdate::Replace(None)
+            ),
+            Error::<Test>::ProductMissingRequiredProp
+        );
+        assert_ok!(ProductRegistry::update_product_props(
+            Origin::signed(owner),
+            id,
+            ProductPropsUpdate::Patch(vec![ProductProperty::new(
+                b"organic",
+                PropValue::Boolean(true)
+            )])
+        ));
+    });
+}
+
+const TEST_CERTIFIER: &str = "Ecocert";
+const TEST_INSPECTOR: &str = "Bob";
+const TEST_CERTIFICATE_ID: &str = "FR-BIO-01/1234";
+const TEST_DOC_HASH: [u8; 32] = [7u8; 32];
+
+fn accredit(certifier: sp_core::sr25519::Public, scheme: &[u8]) {
+    assert_ok!(ProductRegistry::accredit_certifier(
+        Origin::root(),
+        certifier,
+        scheme.to_vec()
+    ));
+}
+
+#[test]
+fn accredit_certifier() {
+    new_test_ext().execute_with(|| {
+        let certifier = account_key(TEST_CERTIFIER);
+
+        accredit(certifier, b"organic");
+
+        assert!(ProductRegistry::is_accredited(&certifier, b"organic".to_vec()));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::CertifierAccredited(
+                certifier,
+                b"organic".to_vec()
+            ))));
+        assert_noop!(
+            ProductRegistry::accredit_certifier(Origin::root(), certifier, b"organic".to_vec()),
+            Error::<Test>::CertifierAlreadyAccredited
+        );
+
+        assert_ok!(ProductRegistry::revoke_accreditation(
+            Origin::root(),
+            certifier,
+            b"organic".to_vec()
+        ));
+
+        assert!(!ProductRegistry::is_accredited(&certifier, b"organic".to_vec()));
+        assert_noop!(
+            ProductRegistry::revoke_accreditation(Origin::root(), certifier, b"organic".to_vec()),
+            Error::<Test>::CertifierNotAccredited
+        );
+    });
+}
+
+#[test]
+fn accredit_certifier_by_organization() {
+    new_test_ext().execute_with(|| {
+        let certifier = account_key(TEST_CERTIFIER);
+
+        // Neither the organization nor its members can accredit it
+        for sender in &[TEST_CERTIFIER, TEST_INSPECTOR] {
+            assert_noop!(
+                ProductRegistry::accredit_certifier(
+                    Origin::signed(account_key(sender)),
+                    certifier,
+                    b"organic".to_vec()
+                ),
+                dispatch::DispatchError::BadOrigin
+            );
+        }
+        assert!(!ProductRegistry::is_accredited(&certifier, b"organic".to_vec()));
+
+        // Nor revoke the accreditation of another certifier
+        accredit(certifier, b"organic");
+        assert_noop!(
+            ProductRegistry::revoke_accreditation(
+                Origin::signed(account_key(TEST_ORGANIZATION)),
+                certifier,
+                b"organic".to_vec()
+            ),
+            dispatch::DispatchError::BadOrigin
+        );
+    });
+}
+
+#[test]
+fn issue_certificate() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let certificate_id = TEST_CERTIFICATE_ID.as_bytes().to_owned();
+        let certifier = account_key(TEST_CERTIFIER);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        store_test_product::<Test>(product_id.clone(), account_key(TEST_ORGANIZATION), now);
+        accredit(certifier, b"organic");
+
+        assert_ok!(ProductRegistry::issue_certificate(
+            Origin::signed(account_key(TEST_INSPECTOR)),
+            certifier,
+            product_id.clone(),
+            certificate_id.clone(),
+            b"organic".to_vec(),
+            40,
+            1000,
+            TEST_DOC_HASH
+        ));
+
+        assert_eq!(
+            ProductRegistry::certificate_by_id(&product_id, &certificate_id),
+            Some(Certificate {
+                id: certificate_id.clone(),
+                product_id: product_id.clone(),
+                certifier,
+                scheme: b"organic".to_vec(),
+                valid_from: 40,
+                valid_until: 1000,
+                doc_hash: TEST_DOC_HASH,
+                issued: now,
+                revoked: None,
+            })
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::CertificateIssued(
+                certifier,
+                product_id.clone(),
+                certificate_id.clone()
+            ))));
+
+        // Certificate IDs are unique per product
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id,
+                certificate_id,
+                b"organic".to_vec(),
+                40,
+                1000,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::CertificateIdExists
+        );
+    });
+}
+
+#[test]
+fn issue_certificate_by_non_accredited_certifier() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let certifier = account_key(TEST_CERTIFIER);
+
+        store_test_product::<Test>(product_id.clone(), account_key(TEST_ORGANIZATION), 42);
+        accredit(certifier, b"organic");
+
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id.clone(),
+                TEST_CERTIFICATE_ID.as_bytes().to_owned(),
+                b"halal".to_vec(),
+                40,
+                1000,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::CertifierNotAccredited
+        );
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id.clone(),
+                TEST_CERTIFICATE_ID.as_bytes().to_owned(),
+                b"organic".to_vec(),
+                1000,
+                40,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::CertificateInvalidValidity
+        );
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id.clone(),
+                TEST_CERTIFICATE_ID.as_bytes().to_owned(),
+                b"organic-eu-2018".to_vec(),
+                40,
+                1000,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::CertificateInvalidScheme
+        );
+
+        // Accreditation is checked on the certifying organization, only organizations being accredited
+        assert_noop!(
+            ProductRegistry::accredit_certifier(
+                Origin::root(),
+                account_key(TEST_INSPECTOR),
+                b"halal".to_vec()
+            ),
+            Error::<Test>::OwnerNotOrganization
+        );
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id.clone(),
+                TEST_CERTIFICATE_ID.as_bytes().to_owned(),
+                b"halal".to_vec(),
+                40,
+                1000,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::CertifierNotAccredited
+        );
+
+        // Only members & delegates can issue certificates on behalf of the certifier
+        assert_noop!(
+            ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_SENDER)),
+                certifier,
+                product_id,
+                TEST_CERTIFICATE_ID.as_bytes().to_owned(),
+                b"organic".to_vec(),
+                40,
+                1000,
+                TEST_DOC_HASH
+            ),
+            Error::<Test>::SenderNotMemberOrDelegate
+        );
+    });
+}
+
+#[test]
+fn revoke_certificate() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let certificate_id = TEST_CERTIFICATE_ID.as_bytes().to_owned();
+        let certifier = account_key(TEST_CERTIFIER);
+        Timestamp::set_timestamp(42);
+
+        store_test_product::<Test>(product_id.clone(), account_key(TEST_ORGANIZATION), 42);
+        accredit(certifier, b"organic");
+        assert_ok!(ProductRegistry::issue_certificate(
+            Origin::signed(account_key(TEST_INSPECTOR)),
+            certifier,
+            product_id.clone(),
+            certificate_id.clone(),
+            b"organic".to_vec(),
+            40,
+            1000,
+            TEST_DOC_HASH
+        ));
+
+        // Only the issuing certifier can revoke the certificate
+        assert_noop!(
+            ProductRegistry::revoke_certificate(
+                Origin::signed(account_key(TEST_ORGANIZATION)),
+                product_id.clone(),
+                certificate_id.clone()
+            ),
+            Error::<Test>::CertificateNotIssuedBySender
+        );
+
+        // Members of the certifier can revoke its certificates
+        Timestamp::set_timestamp(50);
+        assert_ok!(ProductRegistry::revoke_certificate(
+            Origin::signed(account_key(TEST_INSPECTOR)),
+            product_id.clone(),
+            certificate_id.clone()
+        ));
+        assert_eq!(
+            ProductRegistry::certificate_by_id(&product_id, &certificate_id)
+                .and_then(|c| *c.revoked()),
+            Some(50)
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_registry(RawEvent::CertificateRevoked(
+                certifier,
+                product_id.clone(),
+                certificate_id.clone()
+            ))));
+
+        assert_noop!(
+            ProductRegistry::revoke_certificate(Origin::signed(certifier), product_id, certificate_id),
+            Error::<Test>::CertificateAlreadyRevoked
+        );
+    });
+}
+
+#[test]
+fn valid_certificates() {
+    new_test_ext().execute_with(|| {
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let certifier = account_key(TEST_CERTIFIER);
+        Timestamp::set_timestamp(42);
+
+        store_test_product::<Test>(product_id.clone(), account_key(TEST_ORGANIZATION), 42);
+        accredit(certifier, b"organic");
+
+        for (id, valid_from, valid_until) in &[(b"current", 40, 1000), (b"expired", 10, 20), (b"revoked", 40, 1000)] {
+            assert_ok!(ProductRegistry::issue_certificate(
+                Origin::signed(account_key(TEST_INSPECTOR)),
+                certifier,
+                product_id.clone(),
+                id.to_vec(),
+                b"organic".to_vec(),
+                *valid_from,
+                *valid_until,
+                TEST_DOC_HASH
+            ));
+        }
+        assert_ok!(ProductRegistry::revoke_certificate(
+            Origin::signed(certifier),
+            product_id.clone(),
+            b"revoked".to_vec()
+        ));
+
+        let ids = |certificates: Vec<Certificate<_, _>>| -> Vec<CertificateId> {
+            certificates.iter().map(|c| c.id().to_vec()).collect()
+        };
+        assert_eq!(
+            ids(ProductRegistry::valid_certificates(&product_id)),
+            vec![b"current".to_vec()]
+        );
+
+        // Certificates expire
+        Timestamp::set_timestamp(1000);
+        assert!(ProductRegistry::valid_certificates(&product_id).is_empty());
+    });
+}
+
+#[test]
+fn register_products() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let other_owner = account_key(TEST_OTHER_ORGANIZATION);
+
+        assert_ok!(ProductRegistry::register_products(
+            Origin::signed(sender),
+            vec![
+                (b"p1".to_vec(), owner, None, None),
+                (b"p2".to_vec(), other_owner, Some(vec![ProductProperty::text(b"desc", b"p2")]), None),
+            ]
+        ));
+
+        assert_eq!(ProductRegistry::owner_of(b"p1".to_vec()), Some(owner));
+        assert_eq!(ProductRegistry::owner_of(b"p2".to_vec()), Some(other_owner));
+        assert_eq!(ProductRegistry::products_of_org(owner), vec![b"p1".to_vec()]);
+
+        // One event is raised per product
+        for (id, owner) in &[(b"p1".to_vec(), owner), (b"p2".to_vec(), other_owner)] {
+            assert!(System::events().iter().any(|er| er.event
+                == TestEvent::product_registry(RawEvent::ProductRegistered(
+                    sender,
+                    id.clone(),
+                    *owner
+                ))));
+        }
+    });
+}
+
+#[test]
+fn register_products_is_all_or_nothing() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+
+        store_test_product::<Test>(b"p3".to_vec(), owner, 42);
+
+        // A single invalid product fails the whole batch
+        assert_noop!(
+            ProductRegistry::register_products(
+                Origin::signed(sender),
+                vec![
+                    (b"p1".to_vec(), owner, None, None),
+                    (b"p2".to_vec(), owner, None, None),
+                    (b"p3".to_vec(), owner, None, None),
+                ]
+            ),
+            Error::<Test>::ProductIdExists
+        );
+        assert_noop!(
+            ProductRegistry::register_products(
+                Origin::signed(sender),
+                vec![
+                    (b"p1".to_vec(), owner, None, None),
+                    (b"p1".to_vec(), owner, None, None),
+                ]
+            ),
+            Error::<Test>::ProductIdExists
+        );
+        assert_noop!(
+            ProductRegistry::register_products(
+                Origin::signed(sender),
+                vec![
+                    (b"p1".to_vec(), owner, None, None),
+                    (vec![], owner, None, None),
+                ]
+            ),
+            Error::<Test>::ProductIdMissing
+        );
+        assert_eq!(ProductRegistry::product_by_id(b"p1".to_vec()), None);
+
+        assert_noop!(
+            ProductRegistry::register_products(
+                Origin::signed(sender),
+                vec![
+                    (b"p1".to_vec(), owner, None, None),
+                    (b"p2".to_vec(), owner, None, None),
+                    (b"p4".to_vec(), owner, None, None),
+                    (b"p5".to_vec(), owner, None, None),
+                ]
+            ),
+            Error::<Test>::ProductBatchTooLarge
+        );
+    });
+}
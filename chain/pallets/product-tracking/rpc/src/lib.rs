@@ -0,0 +1,167 @@
+//! RPC interface for the Product Tracking pallet.
+//! Exposes the `productTracking_*` methods, so that node operators can inspect the shipping
+//! event notifications the off-chain worker could not deliver, have them delivered again,
+//! and configure the listeners notified by the off-chain worker.
+
+use std::sync::Arc;
+
+use codec::{Decode, Encode};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use parking_lot::RwLock;
+use product_tracking::{
+	Delivery, ListenerEndpoint, ShippingEventIndex, DEAD_LETTERS_KEY, LISTENER_ENDPOINTS_KEY,
+	REPLAYS_KEY,
+};
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sp_core::offchain::{OffchainStorage, STORAGE_PREFIX};
+
+/// A shipping event notification given up by the off-chain worker.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct DeadLetter {
+	pub event_index: ShippingEventIndex,
+	/// Block in which the shipping event was recorded.
+	pub block_number: u32,
+	pub attempts: u32,
+	/// Listener endpoints already notified.
+	pub notified: Vec<String>,
+	pub last_error: Option<String>,
+}
+
+/// Product Tracking RPC methods.
+#[rpc]
+pub trait ProductTrackingApi {
+	/// Returns the shipping event notifications the off-chain worker gave up delivering.
+	#[rpc(name = "productTracking_getDeadLetters")]
+	fn get_dead_letters(&self) -> Result<Vec<DeadLetter>>;
+
+	/// Requests the off-chain worker to deliver the given dead letters again (all of them
+	/// if none is given). Returns the number of dead letters to be replayed.
+	#[rpc(name = "productTracking_replayDeadLetters")]
+	fn replay_dead_letters(&self, event_indices: Option<Vec<ShippingEventIndex>>) -> Result<u32>;
+
+	/// Replaces the listener endpoints notified by the off-chain worker, each given as `URL`,
+	/// or `URL,disabled` to keep the endpoint configured but not notified. An empty list
+	/// disables the notifications (rather than restoring the default listener).
+	#[rpc(name = "productTracking_setListenerEndpoints")]
+	fn set_listener_endpoints(&self, endpoints: Vec<String>) -> Result<()>;
+}
+
+/// Implements the Product Tracking RPC methods, on the offchain local storage of the node.
+pub struct ProductTracking<S> {
+	storage: Arc<RwLock<S>>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<S> ProductTracking<S> {
+	/// Create new `ProductTracking` with the given offchain storage.
+	pub fn new(storage: S, deny_unsafe: DenyUnsafe) -> Self {
+		Self {
+			storage: Arc::new(RwLock::new(storage)),
+			deny_unsafe,
+		}
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The offchain storage could not be decoded.
+	DecodeError,
+	/// The offchain storage was concurrently modified.
+	StorageConflict,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::DecodeError => 1,
+			Error::StorageConflict => 2,
+		}
+	}
+}
+
+fn decode_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::DecodeError.into()),
+		message: "Unable to decode the off-chain worker state.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+fn get<S: OffchainStorage, T: Decode + Default>(storage: &S, key: &[u8]) -> Result<T> {
+	storage
+		.get(STORAGE_PREFIX, key)
+		.map_or(Ok(T::default()), |value| T::decode(&mut &value[..]).map_err(decode_error))
+}
+
+fn decoded(data: &[u8]) -> String {
+	String::from_utf8_lossy(data).into_owned()
+}
+
+impl<S> ProductTrackingApi for ProductTracking<S>
+where
+	S: OffchainStorage + 'static,
+{
+	fn get_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let storage = self.storage.read();
+		let dead_letters: Vec<(ShippingEventIndex, Delivery)> = get(&*storage, DEAD_LETTERS_KEY)?;
+
+		Ok(dead_letters
+			.into_iter()
+			.map(|(idx, delivery)| DeadLetter {
+				event_index: idx,
+				block_number: delivery.block_number,
+				attempts: delivery.attempts,
+				notified: delivery.notified.iter().map(|e| decoded(e)).collect(),
+				last_error: Some(decoded(&delivery.last_error)).filter(|e| !e.is_empty()),
+			})
+			.collect())
+	}
+
+	fn replay_dead_letters(&self, event_indices: Option<Vec<ShippingEventIndex>>) -> Result<u32> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let mut storage = self.storage.write();
+		let dead_letters: Vec<ShippingEventIndex> =
+			get::<_, Vec<(ShippingEventIndex, Delivery)>>(&*storage, DEAD_LETTERS_KEY)?
+				.into_iter()
+				.map(|(idx, _)| idx)
+				.collect();
+		let event_indices: Vec<_> = match event_indices {
+			Some(indices) => indices.into_iter().filter(|idx| dead_letters.contains(idx)).collect(),
+			None => dead_letters,
+		};
+
+		// Append to the replays not processed yet by the off-chain worker
+		let old_value = storage.get(STORAGE_PREFIX, REPLAYS_KEY);
+		let mut replays: Vec<ShippingEventIndex> = get(&*storage, REPLAYS_KEY)?;
+		for idx in event_indices.iter() {
+			if !replays.contains(idx) {
+				replays.push(*idx);
+			}
+		}
+		if !storage.compare_and_set(STORAGE_PREFIX, REPLAYS_KEY, old_value.as_deref(), &replays.encode()) {
+			return Err(RpcError {
+				code: ErrorCode::ServerError(Error::StorageConflict.into()),
+				message: "The off-chain worker state changed, please retry.".into(),
+				data: None,
+			});
+		}
+
+		Ok(event_indices.len() as u32)
+	}
+
+	fn set_listener_endpoints(&self, endpoints: Vec<String>) -> Result<()> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let endpoints: Vec<ListenerEndpoint> =
+			endpoints.iter().map(|endpoint| ListenerEndpoint::parse(endpoint)).collect();
+		self.storage
+			.write()
+			.set(STORAGE_PREFIX, LISTENER_ENDPOINTS_KEY, &endpoints.encode());
+		Ok(())
+	}
+}
@@ -1,6 +1,5 @@
 use crate::types::*;
 use frame_support::sp_std::prelude::*;
-use product_registry::ProductId;
 
 // --- ShipmentBuilder ---
 
@@ -12,8 +11,9 @@ where
 {
     id: ShipmentId,
     owner: AccountId,
-    products: Vec<ProductId>,
+    products: Vec<ShippedProduct>,
     registered: Moment,
+    itinerary: Vec<Leg<AccountId, Moment>>,
 }
 
 impl<AccountId, Moment> ShipmentBuilder<AccountId, Moment>
@@ -31,7 +31,7 @@ where
         self
     }
 
-    pub fn with_products(mut self, products: Vec<ProductId>) -> Self {
+    pub fn with_products(mut self, products: Vec<ShippedProduct>) -> Self {
         self.products = products;
         self
     }
@@ -41,6 +41,11 @@ where
         self
     }
 
+    pub fn with_itinerary(mut self, itinerary: Vec<Leg<AccountId, Moment>>) -> Self {
+        self.itinerary = itinerary;
+        self
+    }
+
     pub fn build(self) -> Shipment<AccountId, Moment> {
         Shipment::<AccountId, Moment> {
             id: self.id,
@@ -49,6 +54,7 @@ where
             registered: self.registered,
             status: ShipmentStatus::Pending,
             delivered: None,
+            itinerary: self.itinerary,
         }
     }
 }
@@ -2,21 +2,33 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::alloc::string::ToString;
+use codec::Encode;
+use fixed::types::I32F32;
 use core::convert::TryInto;
 use frame_support::{
     debug, decl_error, decl_event, decl_module, decl_storage, dispatch, ensure,
     sp_runtime::offchain::{
         self as rt_offchain,
         storage::StorageValueRef,
-        storage_lock::{StorageLock, Time},
+        storage_lock::{BlockAndTime, StorageLock},
+    },
+    sp_runtime::{
+        traits::{IdentifyAccount, Saturating, ValidateUnsigned, Verify},
+        transaction_validity::{
+            InvalidTransaction, TransactionLongevity, TransactionPriority, TransactionSource,
+            TransactionValidity, TransactionValidityError, ValidTransaction,
+        },
+        MultiSignature, MultiSigner,
     },
     sp_std::prelude::*,
-    traits::EnsureOrigin,
+    traits::{EnsureOrigin, Get},
+    weights::Weight,
 };
-use frame_system::{self as system, ensure_signed, offchain::SendTransactionTypes};
+use frame_system::{self as system, ensure_none, ensure_signed, offchain::SendTransactionTypes};
 
-use product_registry::ProductId;
+use product_registry::{
+    OnProductRecall, OrganizationProvider, OwnerError, ProductId, ProductProvider, ProductStatus,
+};
 
 #[cfg(test)]
 mod mock;
@@ -26,20 +38,77 @@ mod tests;
 
 mod types;
 use crate::types::*;
+pub use crate::types::{Delivery, DeliveryStatus, ListenerEndpoint, ShippingEventIndex};
 
 mod builders;
 use crate::builders::*;
 
-// General constraints to limit data size
-// Note: these could also be passed as trait config parameters
-pub const IDENTIFIER_MAX_LENGTH: usize = 36;
-pub const SHIPMENT_MAX_PRODUCTS: usize = 10;
-pub const LISTENER_ENDPOINT: &str = "http://localhost:3005";
-pub const LOCK_TIMEOUT_EXPIRATION: u64 = 3000; // in milli-seconds
+mod migration;
+
+mod notification;
+use crate::notification::*;
+
+pub const LISTENER_ENDPOINT: &str = "http://localhost:3005"; // if none configured
+pub const LISTENER_ENDPOINTS_KEY: &[u8] = b"product_tracking_ocw::listener_endpoints";
+pub const RETRIES_KEY: &[u8] = b"product_tracking_ocw::retries";
+pub const DEAD_LETTERS_KEY: &[u8] = b"product_tracking_ocw::dead_letters";
+pub const REPLAYS_KEY: &[u8] = b"product_tracking_ocw::replays";
+pub const START_BLOCK_KEY: &[u8] = b"product_tracking_ocw::start_block";
+pub const DELIVERY_MAX_ATTEMPTS: u32 = 8;
+pub const DELIVERY_MAX_BACKOFF: u32 = 600; // in blocks
+pub const DEAD_LETTERS_MAX: usize = 500; // oldest ones dropped beyond
+
+// Offchain storage key of the delivery state of a pending shipping event notification
+pub fn delivery_key(idx: ShippingEventIndex) -> Vec<u8> {
+    let mut key = b"product_tracking_ocw::delivery::".to_vec();
+    key.extend(idx.encode());
+    key
+}
+pub const HTTP_TIMEOUT: u64 = 3000; // in milli-seconds
+pub const LOCK_BLOCK_EXPIRATION: u32 = 3; // in blocks
+pub const METERS_PER_DEGREE: u32 = 111_320; // of latitude
 
 pub trait Trait: system::Trait + timestamp::Trait + SendTransactionTypes<Call<Self>> {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
     type CreateRoleOrigin: EnsureOrigin<Self::Origin>;
+    type Products: ProductProvider<Self::AccountId>;
+    type Organizations: OrganizationProvider<Self::AccountId>;
+
+    // General constraints to limit data size
+    type IdentifierMaxLength: Get<u32>;
+    type ShipmentMaxProducts: Get<u32>;
+
+    // Number of blocks during which a custody handover can be accepted
+    type HandoverTimeout: Get<Self::BlockNumber>;
+
+    // Whether shipped products must be owned by the shipment owner or one of its suppliers
+    type EnforceProductOwnership: Get<bool>;
+
+    // Itinerary constraints, the radius (in meters) & time tolerance (in milliseconds)
+    // within which scans are considered to follow the itinerary
+    type ItineraryMaxLegs: Get<u32>;
+    type DeviationRadius: Get<u32>;
+    type DeviationTimeTolerance: Get<Self::Moment>;
+
+    // Unsigned submission of readings by devices: max submissions per device per block,
+    // max readings per submission, and priority & longevity (in blocks) of these transactions
+    type DeviceMaxSubmissions: Get<u32>;
+    type MaxReadingsPerSubmission: Get<u32>;
+    type UnsignedPriority: Get<TransactionPriority>;
+    type UnsignedLongevity: Get<TransactionLongevity>;
+
+    // Number of blocks during which the off-chain worker notifications are kept,
+    // and max number of blocks whose notifications are pruned per block
+    type NotificationRetention: Get<Self::BlockNumber>;
+    type NotificationMaxPrune: Get<u32>;
+
+    // Number of past blocks whose notifications are sent by an off-chain worker
+    // which has not processed any block yet
+    type OcwMaxBacktrackPeriod: Get<Self::BlockNumber>;
+
+    // Max number of shipments flagged per block for product recalls, the shipments
+    // of a recalled product being flagged over as many blocks as needed
+    type MaxShipmentsPerRecall: Get<u32>;
 }
 
 decl_storage! {
@@ -47,6 +116,28 @@ decl_storage! {
         // Shipments
         pub Shipments get(fn shipment_by_id): map hasher(blake2_128_concat) ShipmentId => Option<Shipment<T::AccountId, T::Moment>>;
         pub ShipmentsOfOrganization get(fn shipments_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<ShipmentId>;
+        pub ShipmentsOfProduct get(fn shipments_of_product): map hasher(blake2_128_concat) ProductId => Vec<ShipmentId>;
+        pub RecallsOfShipment get(fn recalls_of_shipment): map hasher(blake2_128_concat) ShipmentId => Vec<ProductId>;
+        // Recalled products whose shipments are still to be flagged, with the time of the recall
+        // & the number of shipments flagged so far
+        pub PendingRecalls get(fn pending_recalls): Vec<(ProductId, T::Moment, u32)>;
+        pub SuppliersOf get(fn suppliers_of): map hasher(blake2_128_concat) T::AccountId => Vec<T::AccountId>;
+
+        // Condition monitoring
+        pub ThresholdsOf get(fn thresholds_of): map hasher(blake2_128_concat) ShipmentId => Vec<ConditionThreshold<T::Moment>>;
+        pub Excursions get(fn excursion_of): double_map hasher(blake2_128_concat) ShipmentId, hasher(blake2_128_concat) ReadingType => Excursion<T::Moment>;
+        pub BreachesOf get(fn breaches_of): map hasher(blake2_128_concat) ShipmentId => Vec<Reading<T::Moment>>;
+        pub Compromised get(fn is_compromised): map hasher(blake2_128_concat) ShipmentId => bool;
+
+        // IoT devices
+        pub Devices get(fn device_by_id): map hasher(blake2_128_concat) DeviceId => Option<Device<T::AccountId>>;
+        pub DevicesOfOrganization get(fn devices_of_org): map hasher(blake2_128_concat) T::AccountId => Vec<DeviceId>;
+        pub DeviceNonces get(fn device_nonce): map hasher(blake2_128_concat) DeviceId => u64;
+        pub DeviceSubmissions get(fn device_submissions): map hasher(blake2_128_concat) DeviceId => (T::BlockNumber, u32);
+
+        // Custody
+        pub Custodians get(fn custodian_of): map hasher(blake2_128_concat) ShipmentId => Option<T::AccountId>;
+        pub Handovers get(fn handover_of): map hasher(blake2_128_concat) ShipmentId => Option<CustodyHandover<T::AccountId, T::BlockNumber>>;
 
         // Shipping events
         pub EventCount get(fn event_count): u128 = 0;
@@ -55,6 +146,11 @@ decl_storage! {
 
         // Off-chain Worker notifications
         pub OcwNotifications get (fn ocw_notifications): map hasher(identity) T::BlockNumber => Vec<ShippingEventIndex>;
+        // Oldest block whose off-chain worker notifications are not pruned yet
+        pub OcwNotificationsPruned get(fn ocw_notifications_pruned): T::BlockNumber;
+
+        // Storage version of the pallet
+        StorageVersion build(|_: &GenesisConfig| Releases::V4_0_0): Releases;
     }
 }
 
@@ -62,9 +158,22 @@ decl_event!(
     pub enum Event<T>
     where
         AccountId = <T as system::Trait>::AccountId,
+        Moment = <T as timestamp::Trait>::Moment,
     {
         ShipmentRegistered(AccountId, ShipmentId, AccountId),
         ShipmentStatusUpdated(AccountId, ShipmentId, ShippingEventIndex, ShipmentStatus),
+        ShipmentRecalled(ShipmentId, ProductId, ShippingEventIndex),
+        CustodyHandoverProposed(AccountId, ShipmentId, AccountId, AccountId),
+        CustodyHandoverDeclined(AccountId, ShipmentId, AccountId),
+        CustodyTransferred(AccountId, ShipmentId, AccountId, AccountId),
+        SupplierAdded(AccountId, AccountId),
+        SupplierRemoved(AccountId, AccountId),
+        ShipmentConditionBreached(ShipmentId, Reading<Moment>),
+        ShipmentLegCompleted(ShipmentId, u32),
+        ShipmentDeviated(ShipmentId, u32, Deviation),
+        DeviceRegistered(AccountId, DeviceId, AccountId),
+        DeviceStatusUpdated(AccountId, DeviceId, bool),
+        ReadingsSubmitted(DeviceId, ShipmentId, ShippingEventIndex),
     }
 );
 
@@ -73,9 +182,44 @@ decl_error! {
         InvalidOrMissingIdentifier,
         ShipmentAlreadyExists,
         ShipmentHasBeenDelivered,
+        ShipmentHasBeenCancelled,
+        ShipmentHasBeenReturned,
+        ShipmentHasBeenLost,
+        ShipmentHasBeenRejected,
+        ShipmentIsPending,
         ShipmentIsInTransit,
+        ShipmentIsDamaged,
+        ShipmentIsClosed,
+        SenderNotCustodian,
+        HandoverAlreadyPending,
+        HandoverToCurrentCustodian,
+        HandoverIsUnknown,
+        HandoverHasExpired,
+        SenderNotHandoverRecipient,
+        SupplierAlreadyAdded,
+        SupplierIsUnknown,
         ShipmentIsUnknown,
         ShipmentHasTooManyProducts,
+        ShipmentHasUnknownProduct,
+        ShipmentHasUnknownLot,
+        ShipmentHasProductOfUnknownSupplier,
+        ShipmentHasInvalidThreshold,
+        ShipmentHasDuplicateThreshold,
+        ShipmentHasTooManyLegs,
+        ShipmentHasInvalidItinerary,
+        ShipmentHasUnknownCarrier,
+        ShipmentHasDiscontinuedProduct,
+        ShipmentHasRecalledProduct,
+        DeviceAlreadyExists,
+        DeviceIsUnknown,
+        DeviceIsInactive,
+        DeviceHasInvalidReadingTypes,
+        ReadingTypeNotSupportedByDevice,
+        ReadingHasInvalidSignature,
+        ReadingOfForeignDevice,
+        SubmissionHasNoReadings,
+        OwnerNotOrganization,
+        SenderNotMemberOrDelegate,
         ShippingEventAlreadyExists,
         ShippingEventMaxExceeded,
         OffchainWorkerAlreadyBusy
@@ -87,16 +231,54 @@ decl_module! {
         type Error = Error<T>;
         fn deposit_event() = default;
 
+        const IdentifierMaxLength: u32 = T::IdentifierMaxLength::get();
+        const ShipmentMaxProducts: u32 = T::ShipmentMaxProducts::get();
+        const HandoverTimeout: T::BlockNumber = T::HandoverTimeout::get();
+        const ItineraryMaxLegs: u32 = T::ItineraryMaxLegs::get();
+        const DeviationRadius: u32 = T::DeviationRadius::get();
+        const DeviationTimeTolerance: T::Moment = T::DeviationTimeTolerance::get();
+        const DeviceMaxSubmissions: u32 = T::DeviceMaxSubmissions::get();
+        const MaxReadingsPerSubmission: u32 = T::MaxReadingsPerSubmission::get();
+        const UnsignedPriority: TransactionPriority = T::UnsignedPriority::get();
+        const UnsignedLongevity: TransactionLongevity = T::UnsignedLongevity::get();
+        const NotificationRetention: T::BlockNumber = T::NotificationRetention::get();
+        const NotificationMaxPrune: u32 = T::NotificationMaxPrune::get();
+        const OcwMaxBacktrackPeriod: T::BlockNumber = T::OcwMaxBacktrackPeriod::get();
+        const MaxShipmentsPerRecall: u32 = T::MaxShipmentsPerRecall::get();
+
+        fn on_runtime_upgrade() -> Weight {
+            migration::migrate_to_v4::<T>()
+        }
+
+        fn on_initialize(now: T::BlockNumber) -> Weight {
+            Self::prune_ocw_notifications(now).saturating_add(Self::flag_recalled_shipments())
+        }
+
         #[weight = 10_000]
-        pub fn register_shipment(origin, id: ShipmentId, owner: T::AccountId, products: Vec<ProductId>) -> dispatch::DispatchResult {
-            T::CreateRoleOrigin::ensure_origin(origin.clone())?;
+        pub fn register_shipment(
+            origin,
+            id: ShipmentId,
+            owner: T::AccountId,
+            products: Vec<ShippedProduct>,
+            thresholds: Vec<ConditionThreshold<T::Moment>>,
+            itinerary: Vec<Leg<T::AccountId, T::Moment>>
+        ) -> dispatch::DispatchResult {
             let who = ensure_signed(origin)?;
 
             // Validate format of shipment ID
             Self::validate_identifier(&id)?;
 
-            // Validate shipment products
-            Self::validate_shipment_products(&products)?;
+            // Validate owner, and that the sender can register shipments on its behalf
+            Self::validate_owner(&who, &owner)?;
+
+            // Validate shipment products (1 DB read per product, +1 per lot, +1 when ownership is enforced)
+            Self::validate_shipment_products(&owner, &products)?;
+
+            // Validate monitoring thresholds
+            Self::validate_thresholds(&thresholds)?;
+
+            // Validate planned itinerary
+            Self::validate_itinerary(&itinerary)?;
 
             // Check shipment doesn't exist yet (1 DB read)
             Self::validate_new_shipment(&id)?;
@@ -107,6 +289,7 @@ decl_module! {
                 .owned_by(owner.clone())
                 .registered_at(<timestamp::Module<T>>::now())
                 .with_products(products)
+                .with_itinerary(itinerary)
                 .build();
             let status = shipment.status.clone();
 
@@ -121,9 +304,22 @@ decl_module! {
 
             // Storage writes
             // --------------
-            // Add shipment (2 DB write)
+            // Add shipment (2 DB write + 1 DB write per distinct product)
+            let mut product_ids: Vec<&ProductId> =
+                shipment.products.iter().map(|product| &product.product_id).collect();
+            product_ids.sort();
+            product_ids.dedup();
+            for product_id in product_ids {
+                ShipmentsOfProduct::append(product_id, &id);
+            }
             <Shipments<T>>::insert(&id, shipment);
             <ShipmentsOfOrganization<T>>::append(&owner, &id);
+            // The owner is the shipment's first custodian (1 DB write)
+            <Custodians<T>>::insert(&id, &owner);
+            // Add monitoring thresholds (1 DB write)
+            if !thresholds.is_empty() {
+                <ThresholdsOf<T>>::insert(&id, thresholds);
+            }
             // Store shipping event (1 DB read, 3 DB writes)
             let event_idx = Self::store_event(event)?;
             // Update offchain notifications (1 DB write)
@@ -143,7 +339,7 @@ decl_module! {
             operation: ShippingOperation,
             #[compact] timestamp: T::Moment,
             location: Option<ReadPoint>,
-            readings: Option<Vec<Reading<T::Moment>>>
+            readings: Option<Vec<SignedReading<T::Moment>>>
         ) -> dispatch::DispatchResult {
             T::CreateRoleOrigin::ensure_origin(origin.clone())?;
             let who = ensure_signed(origin)?;
@@ -151,31 +347,22 @@ decl_module! {
             // Validate format of shipment ID
             Self::validate_identifier(&id)?;
 
+            // Check readings originate from active devices (1 DB read per reading)
+            let readings = Self::validate_readings(&id, readings.unwrap_or_default())?;
+
             // Check shipment is known (1 DB read) & do transition checks
-            let mut shipment = match <Shipments<T>>::get(&id) {
-                Some(shipment) => match shipment.status {
-                    ShipmentStatus::Delivered => Err(<Error<T>>::ShipmentHasBeenDelivered),
-                    ShipmentStatus::InTransit if operation == ShippingOperation::Pickup =>
-                        Err(<Error<T>>::ShipmentIsInTransit),
-                    _ => Ok(shipment)
-                }
-                None => Err(<Error<T>>::ShipmentIsUnknown)
-            }?;
+            let shipment = <Shipments<T>>::get(&id).ok_or(<Error<T>>::ShipmentIsUnknown)?;
+            let status = Self::next_status(&shipment.status, &operation)?;
 
             // Update shipment status
-            shipment = match operation {
-                ShippingOperation::Pickup => shipment.pickup(),
-                ShippingOperation::Deliver => shipment.deliver(timestamp),
-                _ => shipment,
-            };
-            let status = shipment.status.clone();
+            let mut shipment = shipment.with_status(status.clone(), timestamp);
 
             // Create shipping event
             let event = Self::new_shipping_event()
                 .of_type(operation.clone().into())
                 .for_shipment(id.clone())
-                .at_location(location)
-                .with_readings(readings.unwrap_or_default())
+                .at_location(location.clone())
+                .with_readings(readings.clone())
                 .at_time(timestamp)
                 .build();
 
@@ -185,8 +372,24 @@ decl_module! {
             let event_idx = Self::store_event(event)?;
             // Update offchain notifications (1 DB write)
             <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+            // Evaluate readings against monitoring thresholds (1 DB read, +1 DB read/write per reading)
+            Self::monitor_conditions(&id, readings);
+            // Match location against the planned itinerary
+            let leg_completed = match location {
+                Some(location) => Self::follow_itinerary(&id, &mut shipment.itinerary, &location, timestamp),
+                None => false,
+            };
 
-            if operation != ShippingOperation::Scan {
+            if operation == ShippingOperation::Scan {
+                if leg_completed {
+                    // Update shipment's itinerary (1 DB write)
+                    <Shipments<T>>::insert(&id, shipment);
+                }
+            } else {
+                // Closed shipments cannot be handed over anymore (1 DB write)
+                if status.is_final() {
+                    <Handovers<T>>::remove(&id);
+                }
                 // Update shipment (1 DB write)
                 <Shipments<T>>::insert(&id, shipment);
                 // Raise events
@@ -196,17 +399,238 @@ decl_module! {
             Ok(())
         }
 
-        fn offchain_worker(block_number: T::BlockNumber) {
-            // Acquiring the lock
-            let mut lock = StorageLock::<Time>::with_deadline(
-                b"product_tracking_ocw::lock",
-                rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION)
-            );
+        #[weight = 10_000]
+        pub fn propose_handover(origin, id: ShipmentId, to: T::AccountId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check shipment is known & still open (1 DB read)
+            let shipment = <Shipments<T>>::get(&id).ok_or(<Error<T>>::ShipmentIsUnknown)?;
+            ensure!(!shipment.status.is_final(), Error::<T>::ShipmentIsClosed);
 
-            match lock.try_lock() {
-                Ok(_guard) => { Self::process_ocw_notifications(block_number); }
-                Err(_err) => { debug::info!("[product_tracking_ocw] lock is already acquired"); }
+            // Only the current custodian can hand the shipment over (1 DB read)
+            let from = Self::current_custodian(&shipment);
+            ensure!(T::Organizations::acts_for(&who, &from), Error::<T>::SenderNotCustodian);
+            ensure!(from != to, Error::<T>::HandoverToCurrentCustodian);
+            ensure!(T::Organizations::is_organization(&to), Error::<T>::OwnerNotOrganization);
+
+            // An unexpired handover must be accepted or declined first (1 DB read)
+            let now = <system::Module<T>>::block_number();
+            if let Some(handover) = <Handovers<T>>::get(&id) {
+                ensure!(handover.expires < now, Error::<T>::HandoverAlreadyPending);
+            }
+
+            let handover = CustodyHandover {
+                from: from.clone(),
+                to: to.clone(),
+                expires: now.saturating_add(T::HandoverTimeout::get()),
             };
+
+            // Storage writes
+            // --------------
+            // Store handover (1 DB write)
+            <Handovers<T>>::insert(&id, handover);
+            // Store shipping event (1 DB read, 3 DB writes)
+            let event_idx = Self::store_handover_event(&id, ShippingEventType::ShipmentHandoverPropose)?;
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(now, event_idx);
+
+            // Raise events
+            Self::deposit_event(RawEvent::CustodyHandoverProposed(who, id, from, to));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn accept_handover(origin, id: ShipmentId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check handover is pending & the sender is its recipient (1 DB read)
+            let handover = Self::validate_handover(&who, &id)?;
+
+            // Storage writes
+            // --------------
+            // Move custody (2 DB writes)
+            <Handovers<T>>::remove(&id);
+            <Custodians<T>>::insert(&id, &handover.to);
+            // Store shipping event (1 DB read, 3 DB writes)
+            let event_idx = Self::store_handover_event(&id, ShippingEventType::ShipmentHandoverAccept)?;
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+
+            // Raise events
+            Self::deposit_event(RawEvent::CustodyTransferred(who, id, handover.from, handover.to));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn decline_handover(origin, id: ShipmentId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check handover is pending & the sender is its recipient (1 DB read)
+            let handover = Self::validate_handover(&who, &id)?;
+
+            // Storage writes
+            // --------------
+            // Remove handover (1 DB write)
+            <Handovers<T>>::remove(&id);
+            // Store shipping event (1 DB read, 3 DB writes)
+            let event_idx = Self::store_handover_event(&id, ShippingEventType::ShipmentHandoverDecline)?;
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+
+            // Raise events
+            Self::deposit_event(RawEvent::CustodyHandoverDeclined(who, id, handover.to));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn add_supplier(origin, org: T::AccountId, supplier: T::AccountId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate organization, and that the sender can act on its behalf
+            Self::validate_owner(&who, &org)?;
+            ensure!(T::Organizations::is_organization(&supplier), Error::<T>::OwnerNotOrganization);
+
+            // Add supplier of the organization (1 DB read, 1 DB write)
+            let mut suppliers = Self::suppliers_of(&org);
+            ensure!(!suppliers.contains(&supplier), Error::<T>::SupplierAlreadyAdded);
+            suppliers.push(supplier.clone());
+            <SuppliersOf<T>>::insert(&org, suppliers);
+
+            Self::deposit_event(RawEvent::SupplierAdded(org, supplier));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn remove_supplier(origin, org: T::AccountId, supplier: T::AccountId) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate organization, and that the sender can act on its behalf
+            Self::validate_owner(&who, &org)?;
+
+            // Remove supplier of the organization (1 DB read, 1 DB write)
+            let mut suppliers = Self::suppliers_of(&org);
+            let idx = suppliers.iter().position(|s| s == &supplier).ok_or(Error::<T>::SupplierIsUnknown)?;
+            suppliers.remove(idx);
+            <SuppliersOf<T>>::insert(&org, suppliers);
+
+            Self::deposit_event(RawEvent::SupplierRemoved(org, supplier));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn register_device(
+            origin,
+            id: DeviceId,
+            owner: T::AccountId,
+            key: MultiSigner,
+            device_type: DeviceType,
+            reading_types: Vec<ReadingType>
+        ) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Validate format of device ID & type
+            Self::validate_identifier(&id)?;
+            Self::validate_identifier(&device_type)?;
+
+            // Validate device owner, and that the sender can act on its behalf
+            Self::validate_owner(&who, &owner)?;
+
+            // Validate supported types of readings
+            Self::validate_reading_types(&reading_types)?;
+
+            // Check device doesn't exist yet (1 DB read)
+            ensure!(!<Devices<T>>::contains_key(&id), Error::<T>::DeviceAlreadyExists);
+
+            let device = Device {
+                id: id.clone(),
+                owner: owner.clone(),
+                key,
+                device_type,
+                reading_types,
+                active: true,
+            };
+
+            // Storage writes
+            // --------------
+            // Add device (1 DB write)
+            <Devices<T>>::insert(&id, device);
+            // Add device to organization's devices (1 DB write)
+            <DevicesOfOrganization<T>>::append(&owner, &id);
+
+            Self::deposit_event(RawEvent::DeviceRegistered(who, id, owner));
+
+            Ok(())
+        }
+
+        #[weight = 10_000]
+        pub fn update_device_status(origin, id: DeviceId, active: bool) -> dispatch::DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check device is known (1 DB read)
+            let mut device = <Devices<T>>::get(&id).ok_or(Error::<T>::DeviceIsUnknown)?;
+            ensure!(T::Organizations::acts_for(&who, &device.owner), Error::<T>::SenderNotMemberOrDelegate);
+
+            // Update device (1 DB write)
+            device.active = active;
+            <Devices<T>>::insert(&id, device);
+
+            Self::deposit_event(RawEvent::DeviceStatusUpdated(who, id, active));
+
+            Ok(())
+        }
+
+        // Unsigned submission of readings by a device, the signature, nonce & rate limit
+        // being checked by `validate_unsigned`
+        #[weight = T::DbWeight::get()
+            .reads_writes(2, 1)
+            .saturating_mul(payload.readings.len() as Weight)
+            .saturating_add(10_000)]
+        pub fn submit_readings(
+            origin,
+            payload: ReadingsPayload<T::Moment>,
+            _signature: MultiSignature
+        ) -> dispatch::DispatchResult {
+            ensure_none(origin)?;
+
+            // Check the device, shipment & readings (2 DB reads, +1 DB read per reading)
+            Self::validate_submission(&payload)?;
+            let ReadingsPayload { device_id, nonce, shipment_id, readings } = payload;
+
+            // Update device's nonce & submissions in the block (1 DB read, 2 DB writes)
+            let block_number = <system::Module<T>>::block_number();
+            DeviceNonces::insert(&device_id, nonce.saturating_add(1));
+            <DeviceSubmissions<T>>::insert(
+                &device_id,
+                (block_number, Self::submissions_in_block(&device_id).saturating_add(1))
+            );
+
+            // Create shipping event
+            let event = Self::new_shipping_event()
+                .of_type(ShippingEventType::ShipmentScan)
+                .for_shipment(shipment_id.clone())
+                .with_readings(readings.clone())
+                .at_time(<timestamp::Module<T>>::now())
+                .build();
+
+            // Store shipping event (1 DB read, 3 DB writes)
+            let event_idx = Self::store_event(event)?;
+            // Update offchain notifications (1 DB write)
+            <OcwNotifications<T>>::append(block_number, event_idx);
+            // Evaluate readings against monitoring thresholds (1 DB read, +1 DB read/write per reading)
+            Self::monitor_conditions(&shipment_id, readings);
+
+            Self::deposit_event(RawEvent::ReadingsSubmitted(device_id, shipment_id, event_idx));
+
+            Ok(())
+        }
+
+        fn offchain_worker(block_number: T::BlockNumber) {
+            Self::process_ocw_notifications(block_number);
         }
     }
 }
@@ -233,17 +657,262 @@ impl<T: Trait> Module<T> {
         Ok(event_idx)
     }
 
+    fn store_handover_event(
+        id: &ShipmentId,
+        event_type: ShippingEventType,
+    ) -> Result<ShippingEventIndex, Error<T>> {
+        let event = Self::new_shipping_event()
+            .of_type(event_type)
+            .for_shipment(id.clone())
+            .at_location(None)
+            .with_readings(vec![])
+            .at_time(<timestamp::Module<T>>::now())
+            .build();
+        Self::store_event(event)
+    }
+
+    // Updates the shipment's excursions with the readings, and records the readings breaching
+    // the shipment's monitoring thresholds, if any. A breach marks the shipment as compromised.
+    fn monitor_conditions(id: &ShipmentId, readings: Vec<Reading<T::Moment>>) {
+        let thresholds = Self::thresholds_of(id);
+        if thresholds.is_empty() {
+            return;
+        }
+
+        for reading in readings {
+            let threshold = match thresholds.iter().find(|t| t.reading_type == reading.reading_type) {
+                Some(threshold) => threshold,
+                None => continue,
+            };
+
+            let in_range = threshold.in_range(&reading.value);
+            let breached = <Excursions<T>>::mutate(id, &reading.reading_type, |excursion| {
+                // Time between an out of range reading & the next one is spent out of range,
+                // so an excursion can only be measured as exceeded by the reading ending it
+                let ongoing = excursion.out_of_range;
+                if ongoing {
+                    let elapsed = reading.timestamp.saturating_sub(excursion.last_reading);
+                    excursion.duration = excursion.duration.saturating_add(elapsed);
+                } else if !in_range {
+                    excursion.breached = false;
+                }
+                excursion.last_reading = reading.timestamp;
+                excursion.out_of_range = !in_range;
+
+                // A breach is reported once per excursion
+                let breached = (ongoing || !in_range)
+                    && !excursion.breached
+                    && threshold
+                        .max_excursion
+                        .map_or(!in_range, |max_excursion| excursion.duration > max_excursion);
+                excursion.breached |= breached;
+                breached
+            });
+
+            if breached {
+                <BreachesOf<T>>::append(id, &reading);
+                Compromised::insert(id, true);
+                Self::deposit_event(RawEvent::ShipmentConditionBreached(id.clone(), reading));
+            }
+        }
+    }
+
+    // Matches a location of the shipment against the current (i.e. first incomplete) leg of its
+    // itinerary: the leg is completed when the location is within `DeviationRadius` of its destination,
+    // otherwise deviation events are raised when the location is too far from the leg's route, or
+    // outside of its planned time window. Returns whether a leg was completed.
+    // NOTE: distances are approximated on an equirectangular projection (see `ReadPoint::distance_sq`).
+    fn follow_itinerary(
+        id: &ShipmentId,
+        itinerary: &mut Vec<Leg<T::AccountId, T::Moment>>,
+        location: &ReadPoint,
+        timestamp: T::Moment,
+    ) -> bool {
+        let (idx, leg) = match itinerary.iter_mut().enumerate().find(|(_, leg)| leg.completed.is_none()) {
+            Some(current) => current,
+            None => return false,
+        };
+        let idx = idx as u32;
+
+        let tolerance = T::DeviationTimeTolerance::get();
+        if timestamp.saturating_add(tolerance) < leg.departure
+            || timestamp > leg.arrival.saturating_add(tolerance)
+        {
+            Self::deposit_event(RawEvent::ShipmentDeviated(id.clone(), idx, Deviation::OutsideTimeWindow));
+        }
+
+        let radius = I32F32::from_num(T::DeviationRadius::get()) / I32F32::from_num(METERS_PER_DEGREE);
+        let radius_sq = radius * radius;
+        if location.distance_sq(&leg.destination) <= radius_sq {
+            leg.completed = Some(timestamp);
+            Self::deposit_event(RawEvent::ShipmentLegCompleted(id.clone(), idx));
+            return true;
+        }
+        if location.distance_sq_to_segment(&leg.origin, &leg.destination) > radius_sq {
+            Self::deposit_event(RawEvent::ShipmentDeviated(id.clone(), idx, Deviation::OffRoute));
+        }
+        false
+    }
+
+    // The account currently having custody of the shipment, which is its owner
+    // for shipments registered before custody was tracked.
+    pub fn current_custodian(shipment: &Shipment<T::AccountId, T::Moment>) -> T::AccountId {
+        Self::custodian_of(&shipment.id).unwrap_or_else(|| shipment.owner.clone())
+    }
+
     // (Public) Validation methods
     pub fn validate_identifier(id: &[u8]) -> Result<(), Error<T>> {
         // Basic identifier validation
         ensure!(!id.is_empty(), Error::<T>::InvalidOrMissingIdentifier);
         ensure!(
-            id.len() <= IDENTIFIER_MAX_LENGTH,
+            id.len() <= T::IdentifierMaxLength::get() as usize,
             Error::<T>::InvalidOrMissingIdentifier
         );
         Ok(())
     }
 
+    pub fn validate_owner(who: &T::AccountId, owner: &T::AccountId) -> Result<(), Error<T>> {
+        T::Organizations::validate_owner(who, owner).map_err(|e| match e {
+            OwnerError::OwnerNotOrganization => Error::<T>::OwnerNotOrganization,
+            OwnerError::SenderNotMemberOrDelegate => Error::<T>::SenderNotMemberOrDelegate,
+        })
+    }
+
+    pub fn validate_handover(
+        who: &T::AccountId,
+        id: &[u8],
+    ) -> Result<CustodyHandover<T::AccountId, T::BlockNumber>, Error<T>> {
+        let handover = <Handovers<T>>::get(id).ok_or(Error::<T>::HandoverIsUnknown)?;
+        ensure!(
+            handover.expires >= <system::Module<T>>::block_number(),
+            Error::<T>::HandoverHasExpired
+        );
+        ensure!(
+            T::Organizations::acts_for(who, &handover.to),
+            Error::<T>::SenderNotHandoverRecipient
+        );
+        Ok(handover)
+    }
+
+    pub fn validate_thresholds(thresholds: &[ConditionThreshold<T::Moment>]) -> Result<(), Error<T>> {
+        for (idx, threshold) in thresholds.iter().enumerate() {
+            ensure!(
+                threshold.min.is_some() || threshold.max.is_some(),
+                Error::<T>::ShipmentHasInvalidThreshold
+            );
+            if let (Some(min), Some(max)) = (threshold.min, threshold.max) {
+                ensure!(min <= max, Error::<T>::ShipmentHasInvalidThreshold);
+            }
+            // One threshold per type of reading
+            ensure!(
+                !thresholds[..idx]
+                    .iter()
+                    .any(|other| other.reading_type == threshold.reading_type),
+                Error::<T>::ShipmentHasDuplicateThreshold
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_itinerary(itinerary: &[Leg<T::AccountId, T::Moment>]) -> Result<(), Error<T>> {
+        ensure!(
+            itinerary.len() <= T::ItineraryMaxLegs::get() as usize,
+            Error::<T>::ShipmentHasTooManyLegs
+        );
+        for (idx, leg) in itinerary.iter().enumerate() {
+            // Legs are planned, consecutive & chronological
+            ensure!(leg.completed.is_none(), Error::<T>::ShipmentHasInvalidItinerary);
+            ensure!(leg.departure <= leg.arrival, Error::<T>::ShipmentHasInvalidItinerary);
+            if idx > 0 {
+                let previous = &itinerary[idx - 1];
+                ensure!(
+                    previous.destination == leg.origin && previous.arrival <= leg.departure,
+                    Error::<T>::ShipmentHasInvalidItinerary
+                );
+            }
+            ensure!(
+                T::Organizations::is_organization(&leg.carrier),
+                Error::<T>::ShipmentHasUnknownCarrier
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_reading_types(reading_types: &[ReadingType]) -> Result<(), Error<T>> {
+        // At least one type of reading, w/o duplicates
+        ensure!(!reading_types.is_empty(), Error::<T>::DeviceHasInvalidReadingTypes);
+        for (idx, reading_type) in reading_types.iter().enumerate() {
+            ensure!(
+                !reading_types[..idx].contains(reading_type),
+                Error::<T>::DeviceHasInvalidReadingTypes
+            );
+        }
+        Ok(())
+    }
+
+    // Checks each reading originates from an active device supporting its type of reading,
+    // and is signed by the device's key. Returns the readings, stripped of their signature.
+    pub fn validate_readings(
+        id: &[u8],
+        readings: Vec<SignedReading<T::Moment>>,
+    ) -> Result<Vec<Reading<T::Moment>>, Error<T>> {
+        readings
+            .into_iter()
+            .map(|SignedReading { reading, signature }| {
+                let device = Self::validate_reading_device(&reading)?;
+                let payload = (id, &reading.reading_type, &reading.timestamp, &reading.value).encode();
+                ensure!(
+                    Self::signed_by(&device, &payload, &signature),
+                    Error::<T>::ReadingHasInvalidSignature
+                );
+                Ok(reading)
+            })
+            .collect()
+    }
+
+    // Checks the reading originates from an active device supporting its type of reading.
+    fn validate_reading_device(reading: &Reading<T::Moment>) -> Result<Device<T::AccountId>, Error<T>> {
+        let device = <Devices<T>>::get(&reading.device_id).ok_or(Error::<T>::DeviceIsUnknown)?;
+        ensure!(device.active, Error::<T>::DeviceIsInactive);
+        ensure!(
+            device.reading_types.contains(&reading.reading_type),
+            Error::<T>::ReadingTypeNotSupportedByDevice
+        );
+        Ok(device)
+    }
+
+    fn signed_by(device: &Device<T::AccountId>, payload: &[u8], signature: &MultiSignature) -> bool {
+        signature.verify(payload, &device.key.clone().into_account())
+    }
+
+    // Checks a submission of readings, w/o its signature & nonce: the submitting device is active,
+    // the shipment can be scanned, and the readings originate from devices of the same organization.
+    pub fn validate_submission(payload: &ReadingsPayload<T::Moment>) -> Result<(), Error<T>> {
+        let gateway = <Devices<T>>::get(&payload.device_id).ok_or(Error::<T>::DeviceIsUnknown)?;
+        ensure!(gateway.active, Error::<T>::DeviceIsInactive);
+
+        Self::validate_identifier(&payload.shipment_id)?;
+        let shipment = <Shipments<T>>::get(&payload.shipment_id).ok_or(Error::<T>::ShipmentIsUnknown)?;
+        Self::next_status(&shipment.status, &ShippingOperation::Scan)?;
+
+        ensure!(!payload.readings.is_empty(), Error::<T>::SubmissionHasNoReadings);
+        for reading in payload.readings.iter() {
+            let device = Self::validate_reading_device(reading)?;
+            ensure!(device.owner == gateway.owner, Error::<T>::ReadingOfForeignDevice);
+        }
+        Ok(())
+    }
+
+    // Number of readings submissions of the device in the current block.
+    fn submissions_in_block(device_id: &[u8]) -> u32 {
+        let (block_number, count) = Self::device_submissions(device_id);
+        if block_number == <system::Module<T>>::block_number() {
+            count
+        } else {
+            0
+        }
+    }
+
     pub fn validate_new_shipment(id: &[u8]) -> Result<(), Error<T>> {
         // Shipment existence check
         ensure!(
@@ -253,96 +922,614 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
-    pub fn validate_shipment_products(props: &[ProductId]) -> Result<(), Error<T>> {
+    // Shipment state machine, returns the status of the shipment after the operation:
+    // - Pending -> InTransit (Pickup) or Cancelled (Cancel)
+    // - InTransit -> Delivered (Deliver), Rejected (Reject), Lost (ReportLoss) or Damaged (ReportDamage)
+    // - Damaged -> Delivered (Deliver), Rejected (Reject) or Lost (ReportLoss)
+    // - Rejected -> Returned (Return) or Lost (ReportLoss)
+    // - Delivered, Cancelled, Returned & Lost are final.
+    // Scans are only recorded for moving shipments i.e. InTransit, Damaged or Rejected.
+    pub fn next_status(
+        status: &ShipmentStatus,
+        operation: &ShippingOperation,
+    ) -> Result<ShipmentStatus, Error<T>> {
+        use ShipmentStatus::*;
+        use ShippingOperation::*;
+
+        match (status, operation) {
+            (Pending, Pickup) => Ok(InTransit),
+            (Pending, Cancel) => Ok(Cancelled),
+            (Pending, _) => Err(Error::<T>::ShipmentIsPending),
+
+            (InTransit, Scan) => Ok(InTransit),
+            (InTransit, Deliver) => Ok(Delivered),
+            (InTransit, Reject) => Ok(Rejected),
+            (InTransit, ReportLoss) => Ok(Lost),
+            (InTransit, ReportDamage) => Ok(Damaged),
+            (InTransit, _) => Err(Error::<T>::ShipmentIsInTransit),
+
+            (Damaged, Scan) => Ok(Damaged),
+            (Damaged, Deliver) => Ok(Delivered),
+            (Damaged, Reject) => Ok(Rejected),
+            (Damaged, ReportLoss) => Ok(Lost),
+            (Damaged, _) => Err(Error::<T>::ShipmentIsDamaged),
+
+            (Rejected, Scan) => Ok(Rejected),
+            (Rejected, Return) => Ok(Returned),
+            (Rejected, ReportLoss) => Ok(Lost),
+            (Rejected, _) => Err(Error::<T>::ShipmentHasBeenRejected),
+
+            (Delivered, _) => Err(Error::<T>::ShipmentHasBeenDelivered),
+            (Cancelled, _) => Err(Error::<T>::ShipmentHasBeenCancelled),
+            (Returned, _) => Err(Error::<T>::ShipmentHasBeenReturned),
+            (Lost, _) => Err(Error::<T>::ShipmentHasBeenLost),
+        }
+    }
+
+    pub fn validate_shipment_products(
+        owner: &T::AccountId,
+        products: &[ShippedProduct],
+    ) -> Result<(), Error<T>> {
         ensure!(
-            props.len() <= SHIPMENT_MAX_PRODUCTS,
+            products.len() <= T::ShipmentMaxProducts::get() as usize,
             Error::<T>::ShipmentHasTooManyProducts,
         );
+        for product in products {
+            match T::Products::product_status(&product.product_id) {
+                Some(ProductStatus::Active) => Ok(()),
+                Some(ProductStatus::Discontinued) => {
+                    Err(Error::<T>::ShipmentHasDiscontinuedProduct)
+                }
+                Some(ProductStatus::Recalled) => Err(Error::<T>::ShipmentHasRecalledProduct),
+                None => Err(Error::<T>::ShipmentHasUnknownProduct),
+            }?;
+            if let Some(lot_id) = &product.lot_id {
+                ensure!(
+                    T::Products::lot_exists(&product.product_id, lot_id),
+                    Error::<T>::ShipmentHasUnknownLot
+                );
+            }
+        }
+
+        // Products must be owned by the shipment owner, or one of its suppliers
+        if T::EnforceProductOwnership::get() {
+            let suppliers = Self::suppliers_of(owner);
+            for product in products {
+                let product_owner = T::Products::product_owner(&product.product_id)
+                    .ok_or(Error::<T>::ShipmentHasUnknownProduct)?;
+                ensure!(
+                    &product_owner == owner || suppliers.contains(&product_owner),
+                    Error::<T>::ShipmentHasProductOfUnknownSupplier
+                );
+            }
+        }
         Ok(())
     }
 
+    // Flags the shipments of the recalled products, at most `MaxShipmentsPerRecall` shipments
+    // at a time, so that the weight stays bounded however widely a product was shipped.
+    fn flag_recalled_shipments() -> Weight {
+        // (1 DB read)
+        let mut recalls = Self::pending_recalls();
+        if recalls.is_empty() {
+            return T::DbWeight::get().reads(1);
+        }
+
+        let mut remaining = T::MaxShipmentsPerRecall::get() as usize;
+        let mut reads: Weight = 1;
+        let mut writes: Weight = 1;
+        while remaining > 0 && !recalls.is_empty() {
+            let (product_id, recalled, flagged) = &mut recalls[0];
+            // (1 DB read)
+            let shipment_ids = Self::shipments_of_product(&*product_id);
+            reads += 1;
+
+            let start = (*flagged as usize).min(shipment_ids.len());
+            let end = start.saturating_add(remaining).min(shipment_ids.len());
+            for shipment_id in shipment_ids[start..end].iter() {
+                // (1 DB read, 5 DB writes)
+                Self::flag_recalled_shipment(shipment_id.clone(), product_id, *recalled);
+            }
+            reads += (end - start) as Weight;
+            writes += 5 * (end - start) as Weight;
+            remaining -= end - start;
+
+            if end == shipment_ids.len() {
+                recalls.remove(0);
+            } else {
+                *flagged = end as u32;
+            }
+        }
+
+        // (1 DB write)
+        PendingRecalls::<T>::put(recalls);
+        T::DbWeight::get().reads_writes(reads, writes)
+    }
+
+    fn flag_recalled_shipment(shipment_id: ShipmentId, product_id: &ProductId, recalled: T::Moment) {
+        // Create shipping event
+        let event = Self::new_shipping_event()
+            .of_type(ShippingEventType::ShipmentRecall)
+            .for_shipment(shipment_id.clone())
+            .at_location(None)
+            .with_readings(vec![])
+            .at_time(recalled)
+            .build();
+
+        // Store shipping event (1 DB read, 3 DB writes)
+        match Self::store_event(event) {
+            Ok(event_idx) => {
+                // Update offchain notifications (1 DB write)
+                <OcwNotifications<T>>::append(<system::Module<T>>::block_number(), event_idx);
+                // Flag shipment (1 DB write)
+                RecallsOfShipment::append(&shipment_id, product_id);
+
+                Self::deposit_event(RawEvent::ShipmentRecalled(
+                    shipment_id,
+                    product_id.clone(),
+                    event_idx,
+                ));
+            }
+            Err(_) => debug::error!(
+                "[product_tracking] Could not store recall event for shipment {:?}",
+                shipment_id
+            ),
+        }
+    }
+
     // --- Offchain worker methods ---
 
+    // Removes the off-chain worker notifications of the blocks out of the retention window,
+    // at most `NotificationMaxPrune` blocks at a time, so that the weight stays bounded.
+    fn prune_ocw_notifications(now: T::BlockNumber) -> Weight {
+        // Notifications of the blocks before this one are pruned
+        let until = now.saturating_sub(T::NotificationRetention::get());
+        // (1 DB read)
+        let mut next = Self::ocw_notifications_pruned();
+        let mut pruned: u32 = 0;
+
+        while next < until && pruned < T::NotificationMaxPrune::get() {
+            // (1 DB write)
+            <OcwNotifications<T>>::remove(next);
+            next = next.saturating_add(1u32.into());
+            pruned += 1;
+        }
+
+        if pruned == 0 {
+            return T::DbWeight::get().reads(1);
+        }
+        // (1 DB write)
+        <OcwNotificationsPruned<T>>::put(next);
+        T::DbWeight::get().reads_writes(1, pruned as Weight + 1)
+    }
+
     fn process_ocw_notifications(block_number: T::BlockNumber) {
-        // Check last processed block
-        let last_processed_block_ref =
-            StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block");
-        let mut last_processed_block: u32 = match last_processed_block_ref.get::<T::BlockNumber>() {
-            Some(Some(last_proccessed_block)) if last_proccessed_block >= block_number => {
-                debug::info!(
-                    "[product_tracking_ocw] Skipping: Block {:?} has already been processed.",
-                    block_number
-                );
+        let endpoints = Self::listener_endpoints();
+        if endpoints.is_empty() {
+            debug::warn!("[product_tracking_ocw] Skipping: All listener endpoints are disabled.");
+            return;
+        }
+
+        // Acquiring the lock, for long enough to notify every endpoint of an event.
+        // It is extended before each delivery, so a run is never overlapped by the next one.
+        let mut lock = StorageLock::<BlockAndTime<system::Module<T>>>::with_block_and_time_deadline(
+            b"product_tracking_ocw::lock",
+            LOCK_BLOCK_EXPIRATION,
+            rt_offchain::Duration::from_millis(HTTP_TIMEOUT.saturating_mul(endpoints.len() as u64 + 1))
+        );
+        let mut guard = match lock.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                debug::info!("[product_tracking_ocw] lock is already acquired");
                 return;
             }
-            Some(Some(last_proccessed_block)) => {
-                last_proccessed_block.try_into().ok().unwrap() as u32
-            }
-            None => 0u32, //TODO: define a OCW_MAX_BACKTRACK_PERIOD param
-            _ => {
-                debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::last_proccessed_block.");
+        };
+
+        let end_block = block_number.try_into().ok().unwrap() as u32;
+
+        // Check explicit start block (set by the node operator), then last processed block
+        let start_block_ref = StorageValueRef::persistent(START_BLOCK_KEY);
+        let last_processed_block_ref =
+            StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block");
+        let start_block: u32 = match start_block_ref.get::<T::BlockNumber>() {
+            Some(Some(start_block)) => start_block.try_into().ok().unwrap() as u32,
+            Some(None) => {
+                debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::start_block.");
                 return;
             }
+            None => match last_processed_block_ref.get::<T::BlockNumber>() {
+                Some(Some(last_proccessed_block)) if last_proccessed_block >= block_number => {
+                    debug::info!(
+                        "[product_tracking_ocw] Skipping: Block {:?} has already been processed.",
+                        block_number
+                    );
+                    return;
+                }
+                Some(Some(last_proccessed_block)) => {
+                    last_proccessed_block.try_into().ok().unwrap() as u32 + 1
+                }
+                // Nothing processed yet, only catch up with the most recent blocks
+                None => {
+                    let max_backtrack: u32 = T::OcwMaxBacktrackPeriod::get()
+                        .try_into()
+                        .ok()
+                        .unwrap_or(u32::max_value());
+                    end_block.saturating_sub(max_backtrack).max(1)
+                }
+                _ => {
+                    debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::last_proccessed_block.");
+                    return;
+                }
+            },
         };
 
-        let start_block = last_processed_block + 1;
-        let end_block = block_number.try_into().ok().unwrap() as u32;
-        for current_block in start_block..end_block {
+        let retries_ref = StorageValueRef::persistent(RETRIES_KEY);
+        let dead_letters_ref = StorageValueRef::persistent(DEAD_LETTERS_KEY);
+        let mut retries = retries_ref.get::<Vec<ShippingEventIndex>>().flatten().unwrap_or_default();
+        let mut dead_letters = dead_letters_ref
+            .get::<Vec<(ShippingEventIndex, Delivery)>>()
+            .flatten()
+            .unwrap_or_default();
+        // Endpoints not attempted anymore during this run, once unreachable
+        let mut unreachable = Vec::new();
+
+        // Dead letters replayed through RPC are retried right away
+        Self::replay_dead_letters(&mut retries, &mut dead_letters);
+
+        // Retry notifications which failed in previous blocks. Once the lock expired, the run
+        // is aborted, the notifications not attempted yet being left for the next run.
+        let mut aborted = false;
+        let pending = core::mem::take(&mut retries);
+        for (pos, idx) in pending.iter().enumerate() {
+            if guard.extend_lock().is_err() {
+                retries.extend_from_slice(&pending[pos..]);
+                aborted = true;
+                break;
+            }
+            let delivery = Self::deliver(*idx, None, end_block, &endpoints, &mut unreachable);
+            Self::queue_delivery(*idx, delivery, &mut retries, &mut dead_letters);
+        }
+
+        // The notifications of the current block are available to its off-chain worker
+        let mut last_processed_block = None;
+        for current_block in start_block..=end_block {
+            if aborted {
+                break;
+            }
             debug::debug!(
                 "[product_tracking_ocw] Processing notifications for block {}",
                 current_block
             );
-            let ev_indices = Self::ocw_notifications::<T::BlockNumber>(current_block.into());
-
-            let listener_results: Result<Vec<_>, _> = ev_indices
-                .iter()
-                .map(|idx| match Self::event_by_idx(idx) {
-                    Some(ev) => Self::notify_listener(&ev),
-                    None => Ok(()),
-                })
-                .collect();
-
-            if let Err(err) = listener_results {
-                debug::warn!("[product_tracking_ocw] notify_listener error: {}", err);
-                break;
+            let notifications = Self::ocw_notifications::<T::BlockNumber>(current_block.into());
+            for (pos, idx) in notifications.iter().enumerate() {
+                if guard.extend_lock().is_err() {
+                    // The rest of the block is queued for retry, so the block is processed
+                    for idx in notifications[pos..].iter() {
+                        StorageValueRef::persistent(&delivery_key(*idx))
+                            .set(&Self::new_delivery(current_block, end_block));
+                        retries.push(*idx);
+                    }
+                    aborted = true;
+                    break;
+                }
+                let delivery = Self::deliver(*idx, Some(current_block), end_block, &endpoints, &mut unreachable);
+                Self::queue_delivery(*idx, delivery, &mut retries, &mut dead_letters);
             }
-            last_processed_block = current_block;
+            last_processed_block = Some(current_block);
+        }
+        if aborted {
+            debug::error!("[product_tracking_ocw] Lock expired, aborting the run after saving its progress.");
         }
 
-        // Save last processed block
-        if last_processed_block >= start_block {
-            last_processed_block_ref.set(&last_processed_block);
+        // Save failed notifications & last processed block
+        retries_ref.set(&retries);
+        dead_letters_ref.set(&dead_letters);
+        if let Some(last_processed_block) = last_processed_block {
+            last_processed_block_ref.set(&T::BlockNumber::from(last_processed_block));
+            start_block_ref.clear();
             debug::info!(
-                "[product_tracking_ocw] Notifications successfully processed up to block {}",
-                last_processed_block
+                "[product_tracking_ocw] Notifications processed up to block {}, {} to retry, {} dead letters",
+                last_processed_block,
+                retries.len(),
+                dead_letters.len()
             );
         }
     }
 
-    fn notify_listener(ev: &ShippingEvent<T::Moment>) -> Result<(), &'static str> {
-        debug::info!("notifying listener: {:?}", ev);
+    // Queues a notification whose delivery is still pending for retry, or as a dead letter
+    // (along with its delivery state) once given up.
+    fn queue_delivery(
+        idx: ShippingEventIndex,
+        delivery: Delivery,
+        retries: &mut Vec<ShippingEventIndex>,
+        dead_letters: &mut Vec<(ShippingEventIndex, Delivery)>,
+    ) {
+        match delivery.status {
+            DeliveryStatus::Pending => retries.push(idx),
+            DeliveryStatus::DeadLetter => {
+                dead_letters.push((idx, delivery));
+                if dead_letters.len() > DEAD_LETTERS_MAX {
+                    let (dropped, _) = dead_letters.remove(0);
+                    debug::warn!("[product_tracking_ocw] Too many dead letters, dropping event {}", dropped);
+                }
+            }
+            DeliveryStatus::Delivered => (),
+        }
+    }
+
+    // Delivery state of a new notification of an event of the given block.
+    fn new_delivery(block_number: u32, now: u32) -> Delivery {
+        Delivery {
+            block_number,
+            block_hash: Self::block_hash_of(block_number),
+            // The parent hash is known (unlike the block's own hash) even for the current block,
+            // so it is kept from the first attempt on
+            parent_hash: block_number.checked_sub(1).and_then(Self::block_hash_of),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: now,
+            notified: Vec::new(),
+            last_error: Vec::new(),
+        }
+    }
+
+    // Attempts to deliver the notification of a shipping event to the endpoints not notified yet,
+    // and updates its delivery state: failed deliveries are retried with an exponential backoff,
+    // until `DELIVERY_MAX_ATTEMPTS` attempts. `block_number` is only given for new notifications,
+    // whose delivery state may exist already (if attempted by an aborted run).
+    // The delivery state is only kept under `delivery_key` while the delivery is pending.
+    fn deliver(
+        idx: ShippingEventIndex,
+        block_number: Option<u32>,
+        now: u32,
+        endpoints: &[Vec<u8>],
+        unreachable: &mut Vec<Vec<u8>>,
+    ) -> Delivery {
+        let key = delivery_key(idx);
+        let delivery_ref = StorageValueRef::persistent(&key);
+        let mut delivery = match (block_number, delivery_ref.get::<Delivery>().flatten()) {
+            // Endpoints notified by an aborted run are not notified again
+            (_, Some(delivery)) => delivery,
+            (Some(block_number), None) => Self::new_delivery(block_number, now),
+            (None, None) => {
+                debug::error!("[product_tracking_ocw] Missing delivery state of event {}", idx);
+                return Delivery {
+                    block_number: 0,
+                    block_hash: None,
+                    parent_hash: None,
+                    status: DeliveryStatus::DeadLetter,
+                    attempts: 0,
+                    next_attempt: now,
+                    notified: Vec::new(),
+                    last_error: b"missing delivery state".to_vec(),
+                };
+            }
+        };
+        if delivery.next_attempt > now {
+            return delivery;
+        }
 
-        let request =
-            sp_runtime::offchain::http::Request::post(&LISTENER_ENDPOINT, vec![ev.to_string()]);
+        let ev = match Self::event_by_idx(idx) {
+            Some(ev) => ev,
+            None => {
+                debug::error!("[product_tracking_ocw] Unknown event {}", idx);
+                delivery.status = DeliveryStatus::DeadLetter;
+                delivery.last_error = b"unknown event".to_vec();
+                delivery_ref.clear();
+                return delivery;
+            }
+        };
+        // The block's hash becomes known once the next block is imported
+        if delivery.block_hash.is_none() {
+            delivery.block_hash = Self::block_hash_of(delivery.block_number);
+        }
+        let payload = Notification {
+            event_idx: idx,
+            block_number: delivery.block_number,
+            block_hash: delivery.block_hash.as_ref(),
+            parent_hash: delivery.parent_hash.as_ref(),
+            event: &ev,
+        }
+        .to_json();
+
+        let mut failed = false;
+        for endpoint in endpoints.iter() {
+            if delivery.notified.contains(endpoint) || unreachable.contains(endpoint) {
+                continue;
+            }
+            match Self::notify_listener(endpoint, &payload) {
+                Ok(()) => delivery.notified.push(endpoint.clone()),
+                Err(err) => {
+                    debug::warn!("[product_tracking_ocw] notify_listener error for event {}: {:?}", idx, err);
+                    delivery.last_error = match err {
+                        NotifyError::Unreachable(msg) => {
+                            unreachable.push(endpoint.clone());
+                            msg.as_bytes().to_vec()
+                        }
+                        NotifyError::Rejected(_) => b"http response error".to_vec(),
+                    };
+                    failed = true;
+                }
+            }
+        }
+
+        delivery.status = if endpoints.iter().all(|endpoint| delivery.notified.contains(endpoint)) {
+            DeliveryStatus::Delivered
+        } else if failed {
+            // Exponential backoff, the endpoints skipped as unreachable not counting as an attempt
+            delivery.attempts += 1;
+            let backoff = 1u32
+                .checked_shl(delivery.attempts - 1)
+                .unwrap_or(DELIVERY_MAX_BACKOFF)
+                .min(DELIVERY_MAX_BACKOFF);
+            delivery.next_attempt = now.saturating_add(backoff);
+            if delivery.attempts >= DELIVERY_MAX_ATTEMPTS {
+                DeliveryStatus::DeadLetter
+            } else {
+                DeliveryStatus::Pending
+            }
+        } else {
+            DeliveryStatus::Pending
+        };
+        match delivery.status {
+            DeliveryStatus::Pending => delivery_ref.set(&delivery),
+            DeliveryStatus::Delivered | DeliveryStatus::DeadLetter => delivery_ref.clear(),
+        }
+        delivery
+    }
+
+    // Hash of the block, if known: the hash of a block is stored by the next one, and pruned
+    // after `BlockHashCount` blocks.
+    fn block_hash_of(block_number: u32) -> Option<Vec<u8>> {
+        let hash = <system::Module<T>>::block_hash(T::BlockNumber::from(block_number));
+        if hash == T::Hash::default() {
+            None
+        } else {
+            Some(hash.as_ref().to_vec())
+        }
+    }
+
+    // Moves the dead letters whose replay has been requested (through RPC) to the retries,
+    // restoring their delivery state.
+    fn replay_dead_letters(
+        retries: &mut Vec<ShippingEventIndex>,
+        dead_letters: &mut Vec<(ShippingEventIndex, Delivery)>,
+    ) {
+        let replays_ref = StorageValueRef::persistent(REPLAYS_KEY);
+        let mut replays = Vec::new();
+        let taken = replays_ref.mutate(|requested: Option<Option<Vec<ShippingEventIndex>>>| {
+            replays = requested.flatten().unwrap_or_default();
+            Ok::<_, ()>(Vec::<ShippingEventIndex>::new())
+        });
+        if !matches!(taken, Ok(Ok(_))) {
+            return;
+        }
+
+        for idx in replays {
+            let pos = match dead_letters.iter().position(|(dead_letter, _)| *dead_letter == idx) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let (_, mut delivery) = dead_letters.remove(pos);
+
+            delivery.status = DeliveryStatus::Pending;
+            delivery.attempts = 0;
+            delivery.next_attempt = 0;
+            StorageValueRef::persistent(&delivery_key(idx)).set(&delivery);
+            retries.push(idx);
+        }
+    }
+
+    // The URLs of the enabled listener endpoints, as configured in the offchain local storage
+    // (e.g. with the `--tracking-listener-url` flag of the node), or `LISTENER_ENDPOINT` by default.
+    pub fn listener_endpoints() -> Vec<Vec<u8>> {
+        let endpoints_ref = StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY);
+        match endpoints_ref.get::<Vec<ListenerEndpoint>>() {
+            // An empty list disables the notifications, rather than falling back to the default
+            Some(Some(endpoints)) => endpoints
+                .into_iter()
+                .filter(|endpoint| endpoint.enabled)
+                .map(|endpoint| endpoint.url)
+                .collect(),
+            Some(None) => {
+                debug::error!("[product_tracking_ocw] Error reading product_tracking_ocw::listener_endpoints.");
+                vec![LISTENER_ENDPOINT.as_bytes().to_vec()]
+            }
+            _ => vec![LISTENER_ENDPOINT.as_bytes().to_vec()],
+        }
+    }
+
+    fn notify_listener(endpoint: &[u8], payload: &str) -> Result<(), NotifyError> {
+        let endpoint = core::str::from_utf8(endpoint)
+            .map_err(|_| NotifyError::Unreachable("invalid listener endpoint"))?;
+        debug::info!("notifying listener {}: {}", endpoint, payload);
+
+        let request = sp_runtime::offchain::http::Request::post(endpoint, vec![payload]);
 
         let timeout =
-            sp_io::offchain::timestamp().add(sp_runtime::offchain::Duration::from_millis(3000));
+            sp_io::offchain::timestamp().add(sp_runtime::offchain::Duration::from_millis(HTTP_TIMEOUT));
 
         let pending = request
-            .add_header(&"Content-Type", &"text/plain")
+            .add_header(&"Content-Type", &"application/json")
             .deadline(timeout) // Setting the timeout time
             .send() // Sending the request out by the host
-            .map_err(|_| "http post request building error")?;
+            .map_err(|_| NotifyError::Unreachable("http post request building error"))?;
 
         let response = pending
             .try_wait(timeout)
-            .map_err(|_| "http post request sent error")?
-            .map_err(|_| "http post request sent error")?;
+            .map_err(|_| NotifyError::Unreachable("http post request sent error"))?
+            .map_err(|_| NotifyError::Unreachable("http post request sent error"))?;
 
         if response.code != 200 {
-            return Err("http response error");
+            return Err(NotifyError::Rejected(response.code));
         }
 
         Ok(())
     }
 }
+
+impl<T: Trait> ValidateUnsigned for Module<T> {
+    type Call = Call<T>;
+
+    fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+        let (payload, signature) = match call {
+            Call::submit_readings(payload, signature) => (payload, signature),
+            _ => return InvalidTransaction::Call.into(),
+        };
+
+        // Check the payload is signed by a registered device
+        let device = Self::device_by_id(&payload.device_id).ok_or(InvalidTransaction::BadProof)?;
+        ensure!(
+            Self::signed_by(&device, &payload.encode(), signature),
+            InvalidTransaction::BadProof
+        );
+
+        // Replay protection, & rate limit of the device
+        let next_nonce = Self::device_nonce(&payload.device_id);
+        ensure!(payload.nonce >= next_nonce, InvalidTransaction::Stale);
+        ensure!(
+            Self::submissions_in_block(&payload.device_id) < T::DeviceMaxSubmissions::get(),
+            InvalidTransaction::ExhaustsResources
+        );
+        ensure!(
+            payload.readings.len() <= T::MaxReadingsPerSubmission::get() as usize,
+            InvalidTransaction::ExhaustsResources
+        );
+
+        Self::validate_submission(payload).map_err(|_| InvalidTransaction::Call)?;
+
+        // Submissions of a device are ordered by nonce
+        let mut valid_tx = ValidTransaction::with_tag_prefix("ProductTracking")
+            .priority(T::UnsignedPriority::get())
+            .and_provides((&payload.device_id, payload.nonce))
+            .longevity(T::UnsignedLongevity::get())
+            .propagate(true);
+        if payload.nonce > next_nonce {
+            valid_tx = valid_tx.and_requires((&payload.device_id, payload.nonce - 1));
+        }
+        valid_tx.build()
+    }
+
+    fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+        // Only the device's next submission can be included in a block
+        if let Call::submit_readings(payload, _) = call {
+            ensure!(
+                payload.nonce <= Self::device_nonce(&payload.device_id),
+                InvalidTransaction::Future
+            );
+        }
+        Self::validate_unsigned(TransactionSource::InBlock, call).map(|_| ())
+    }
+}
+
+impl<T: Trait> OnProductRecall for Module<T> {
+    fn on_product_recall(id: &ProductId) {
+        // Every shipment containing the recalled product is flagged, from the next block on
+        <PendingRecalls<T>>::append((id, <timestamp::Module<T>>::now(), 0u32));
+    }
+
+    fn on_product_recall_weight(_id: &ProductId) -> Weight {
+        // 1 DB write, the shipments being flagged by `on_initialize`
+        T::DbWeight::get().writes(1)
+    }
+}
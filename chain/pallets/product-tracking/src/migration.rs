@@ -0,0 +1,126 @@
+// Storage migrations run on runtime upgrades
+
+use crate::*;
+use frame_support::{
+    storage::{IterableStorageDoubleMap, IterableStorageMap},
+    traits::Get,
+    StorageMap, StorageValue,
+};
+
+// Shipment as stored before products could reference a lot
+#[derive(Decode)]
+struct ShipmentV1<AccountId, Moment> {
+    id: ShipmentId,
+    owner: AccountId,
+    status: ShipmentStatus,
+    products: Vec<ProductId>,
+    registered: Moment,
+    delivered: Option<Moment>,
+}
+
+// Shipment as stored before itineraries were introduced
+#[derive(Decode)]
+struct ShipmentV2<AccountId, Moment> {
+    id: ShipmentId,
+    owner: AccountId,
+    status: ShipmentStatus,
+    products: Vec<ShippedProduct>,
+    registered: Moment,
+    delivered: Option<Moment>,
+}
+
+// Excursion as stored before breaches were reported once per excursion
+#[derive(Decode)]
+struct ExcursionV3<Moment> {
+    last_reading: Moment,
+    out_of_range: bool,
+    duration: Moment,
+}
+
+// Indexes a shipment by its products, unless already done.
+fn index_shipment_products(shipment_id: &ShipmentId, product_ids: &[ProductId]) {
+    for product_id in product_ids {
+        ShipmentsOfProduct::mutate(product_id, |shipment_ids| {
+            if !shipment_ids.contains(shipment_id) {
+                shipment_ids.push(shipment_id.clone());
+            }
+        });
+    }
+}
+
+// Migrates storage to the latest version:
+// - V2 converts the products of all shipments to shipped products w/o lot,
+//   and indexes the shipments by product (for product recalls).
+// - V3 adds an empty itinerary to all shipments.
+// - V4 flags the ongoing excursions whose breach was already reported.
+pub fn migrate_to_v4<T: Trait>() -> Weight {
+    let mut count: Weight = 0;
+    match StorageVersion::get() {
+        Releases::V1_0_0 => {
+            <Shipments<T>>::translate::<ShipmentV1<T::AccountId, T::Moment>, _>(|_id, shipment| {
+                index_shipment_products(&shipment.id, &shipment.products);
+                Some(Shipment {
+                    id: shipment.id,
+                    owner: shipment.owner,
+                    status: shipment.status,
+                    products: shipment
+                        .products
+                        .into_iter()
+                        .map(|product_id| ShippedProduct {
+                            product_id,
+                            lot_id: None,
+                        })
+                        .collect(),
+                    registered: shipment.registered,
+                    delivered: shipment.delivered,
+                    itinerary: vec![],
+                })
+            });
+            // (1 DB read & write per shipment, +1 per shipped product)
+            count = <Shipments<T>>::iter_values()
+                .map(|shipment| shipment.products.len() as Weight + 1)
+                .sum();
+        }
+        Releases::V2_0_0 => {
+            <Shipments<T>>::translate::<ShipmentV2<T::AccountId, T::Moment>, _>(|_id, shipment| {
+                Some(Shipment {
+                    id: shipment.id,
+                    owner: shipment.owner,
+                    status: shipment.status,
+                    products: shipment.products,
+                    registered: shipment.registered,
+                    delivered: shipment.delivered,
+                    itinerary: vec![],
+                })
+            });
+            // (1 DB read & write per shipment)
+            count = <Shipments<T>>::iter().count() as Weight;
+        }
+        Releases::V3_0_0 => (),
+        Releases::V4_0_0 => return 0,
+    }
+
+    // Up to V3, a breach was reported for every out of range reading once the threshold was exceeded
+    <Excursions<T>>::translate::<ExcursionV3<T::Moment>, _>(|id, reading_type, excursion| {
+        let breached = excursion.out_of_range
+            && <Module<T>>::thresholds_of(&id)
+                .iter()
+                .find(|threshold| threshold.reading_type == reading_type)
+                .map_or(false, |threshold| {
+                    threshold
+                        .max_excursion
+                        .map_or(true, |max_excursion| excursion.duration > max_excursion)
+                });
+        Some(Excursion {
+            last_reading: excursion.last_reading,
+            out_of_range: excursion.out_of_range,
+            duration: excursion.duration,
+            breached,
+        })
+    });
+    StorageVersion::put(Releases::V4_0_0);
+
+    // (2 DB reads & 1 DB write per excursion)
+    let excursions = <Excursions<T>>::iter().count() as Weight;
+    T::DbWeight::get().reads_writes(count + 2 * excursions + 1, count + excursions + 1)
+}
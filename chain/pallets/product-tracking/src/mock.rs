@@ -2,8 +2,12 @@
 
 use crate::{Call, Module, Trait};
 use core::marker::PhantomData;
+use core::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use frame_support::{
-    impl_outer_event, impl_outer_origin, parameter_types, traits::EnsureOrigin, weights::Weight,
+    impl_outer_event, impl_outer_origin, parameter_types,
+    traits::{EnsureOrigin, Get},
+    weights::Weight,
 };
 use frame_system as system;
 use frame_system::RawOrigin;
@@ -23,6 +27,7 @@ use sp_runtime::{
 };
 
 use crate as product_tracking;
+use product_registry::{LotId, OrganizationProvider, ProductId, ProductProvider, ProductStatus};
 
 impl_outer_origin! {
     pub enum Origin for Test {}
@@ -46,6 +51,20 @@ parameter_types! {
     pub const MaximumBlockWeight: Weight = 1024;
     pub const MaximumBlockLength: u32 = 2 * 1024;
     pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+    pub const IdentifierMaxLength: u32 = 36;
+    pub const ShipmentMaxProducts: u32 = 10;
+    pub const HandoverTimeout: u64 = 10;
+    pub const ItineraryMaxLegs: u32 = 3;
+    pub const DeviationRadius: u32 = 1_000;
+    pub const DeviationTimeTolerance: u64 = 10;
+    pub const DeviceMaxSubmissions: u32 = 2;
+    pub const MaxReadingsPerSubmission: u32 = 3;
+    pub const UnsignedPriority: u64 = 100;
+    pub const UnsignedLongevity: u64 = 5;
+    pub const NotificationRetention: u64 = 5;
+    pub const NotificationMaxPrune: u32 = 2;
+    pub const OcwMaxBacktrackPeriod: u64 = 3;
+    pub const MaxShipmentsPerRecall: u32 = 2;
 }
 
 impl system::Trait for Test {
@@ -86,6 +105,23 @@ impl timestamp::Trait for Test {
 impl Trait for Test {
     type Event = TestEvent;
     type CreateRoleOrigin = MockOrigin<Test>;
+    type Products = MockProducts;
+    type Organizations = MockOrganizations;
+    type IdentifierMaxLength = IdentifierMaxLength;
+    type ShipmentMaxProducts = ShipmentMaxProducts;
+    type HandoverTimeout = HandoverTimeout;
+    type EnforceProductOwnership = MockProducts;
+    type ItineraryMaxLegs = ItineraryMaxLegs;
+    type DeviationRadius = DeviationRadius;
+    type DeviationTimeTolerance = DeviationTimeTolerance;
+    type DeviceMaxSubmissions = DeviceMaxSubmissions;
+    type MaxReadingsPerSubmission = MaxReadingsPerSubmission;
+    type UnsignedPriority = UnsignedPriority;
+    type UnsignedLongevity = UnsignedLongevity;
+    type NotificationRetention = NotificationRetention;
+    type NotificationMaxPrune = NotificationMaxPrune;
+    type OcwMaxBacktrackPeriod = OcwMaxBacktrackPeriod;
+    type MaxShipmentsPerRecall = MaxShipmentsPerRecall;
 }
 
 pub type ProductTracking = Module<Test>;
@@ -104,16 +140,90 @@ impl<T: Trait> EnsureOrigin<T::Origin> for MockOrigin<T> {
     }
 }
 
+thread_local! {
+    static PRODUCTS: RefCell<BTreeMap<ProductId, (sr25519::Public, ProductStatus)>> = RefCell::new(BTreeMap::new());
+    static LOTS: RefCell<BTreeSet<(ProductId, LotId)>> = RefCell::new(BTreeSet::new());
+    static ENFORCE_OWNERSHIP: RefCell<bool> = RefCell::new(false);
+    static ORGANIZATIONS: RefCell<BTreeMap<sr25519::Public, Vec<sr25519::Public>>> = RefCell::new(BTreeMap::new());
+}
+
+// Stubs the product registry, products are registered through `MockProducts::store`
+// (and their lots through `MockProducts::store_lot`), as well as whether product ownership is enforced.
+pub struct MockProducts;
+
+impl MockProducts {
+    pub fn store(id: ProductId, owner: sr25519::Public, status: ProductStatus) {
+        PRODUCTS.with(|products| products.borrow_mut().insert(id, (owner, status)));
+    }
+
+    pub fn store_lot(product_id: &[u8], lot_id: &[u8]) {
+        LOTS.with(|lots| lots.borrow_mut().insert((product_id.to_vec(), lot_id.to_vec())));
+    }
+
+    pub fn enforce_ownership(enforce: bool) {
+        ENFORCE_OWNERSHIP.with(|v| *v.borrow_mut() = enforce);
+    }
+}
+
+impl ProductProvider<sr25519::Public> for MockProducts {
+    fn product_status(id: &ProductId) -> Option<ProductStatus> {
+        PRODUCTS.with(|products| products.borrow().get(id).map(|(_, status)| status.clone()))
+    }
+
+    fn product_owner(id: &ProductId) -> Option<sr25519::Public> {
+        PRODUCTS.with(|products| products.borrow().get(id).map(|(owner, _)| *owner))
+    }
+
+    fn lot_exists(product_id: &ProductId, lot_id: &LotId) -> bool {
+        LOTS.with(|lots| lots.borrow().contains(&(product_id.clone(), lot_id.clone())))
+    }
+}
+
+impl Get<bool> for MockProducts {
+    fn get() -> bool {
+        ENFORCE_OWNERSHIP.with(|v| *v.borrow())
+    }
+}
+
+// Stubs the consortium organizations & their members (or delegates).
+pub struct MockOrganizations;
+
+impl MockOrganizations {
+    pub fn add_member(org: sr25519::Public, account: sr25519::Public) {
+        ORGANIZATIONS.with(|orgs| orgs.borrow_mut().entry(org).or_default().push(account));
+    }
+}
+
+impl OrganizationProvider<sr25519::Public> for MockOrganizations {
+    fn is_organization(org: &sr25519::Public) -> bool {
+        ORGANIZATIONS.with(|orgs| orgs.borrow().contains_key(org))
+    }
+
+    fn is_member_or_delegate(org: &sr25519::Public, account: &sr25519::Public) -> bool {
+        ORGANIZATIONS.with(|orgs| {
+            orgs.borrow()
+                .get(org)
+                .map_or(false, |members| members.contains(account))
+        })
+    }
+}
+
 // This function basically just builds a genesis storage key/value store according to
 // our desired mockup.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-    let storage = frame_system::GenesisConfig::default()
+    let mut storage = frame_system::GenesisConfig::default()
         .build_storage::<Test>()
         .unwrap();
+    product_tracking::GenesisConfig::default()
+        .assimilate_storage::<Test>(&mut storage)
+        .unwrap();
     let mut ext = sp_io::TestExternalities::from(storage);
     // Events are not emitted on block 0 -> advance to block 1.
     // Any dispatchable calls made during genesis block will have no events emitted.
     ext.execute_with(|| System::set_block_number(1));
+    // Test organizations, Alice acting on behalf of Northwind.
+    MockOrganizations::add_member(account_key("Northwind"), account_key("Alice"));
+    MockOrganizations::add_member(account_key("Contoso"), account_key("Bob"));
     ext
 }
 
@@ -0,0 +1,128 @@
+// Versioned JSON notifications of the shipping events, sent to the listeners by the off-chain worker
+
+use crate::types::*;
+use codec::alloc::string::String;
+use core::fmt::{self, Write};
+use frame_support::sp_runtime::traits::UniqueSaturatedInto;
+
+// To be increased on any breaking change of the notifications' format
+pub const NOTIFICATION_VERSION: u32 = 2;
+
+pub struct Notification<'a, Moment, BlockNumber, Hash> {
+    pub event_idx: ShippingEventIndex,
+    pub block_number: BlockNumber,
+    // Hash of the block, for listeners to detect reorgs, unless notified by the block's own
+    // off-chain worker (to which it is unknown yet)
+    pub block_hash: Option<Hash>,
+    // Hash of the parent block, identifying the block (along with its number) across forks,
+    // unless unknown (for the genesis block, or once pruned)
+    pub parent_hash: Option<Hash>,
+    pub event: &'a ShippingEvent<Moment>,
+}
+
+impl<'a, Moment, BlockNumber, Hash> Notification<'a, Moment, BlockNumber, Hash>
+where
+    Moment: UniqueSaturatedInto<u64> + Copy,
+    BlockNumber: UniqueSaturatedInto<u64> + Copy,
+    Hash: AsRef<[u8]>,
+{
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        // Writing to a string cannot fail
+        let _ = self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) -> fmt::Result {
+        let ev = self.event;
+        write!(
+            json,
+            r#"{{"version":{},"event_index":{},"block_number":{},"block_hash":{},"parent_hash":{},"#,
+            NOTIFICATION_VERSION,
+            self.event_idx,
+            number(self.block_number),
+            JsonHash(self.block_hash.as_ref().map(AsRef::as_ref)),
+            JsonHash(self.parent_hash.as_ref().map(AsRef::as_ref))
+        )?;
+        write!(
+            json,
+            r#""event":{{"type":"{}","shipment_id":{},"timestamp":{},"location":"#,
+            ev.event_type.name(),
+            JsonId(&ev.shipment_id),
+            number(ev.timestamp)
+        )?;
+        match &ev.location {
+            Some(location) => write!(
+                json,
+                r#"{{"latitude":{},"longitude":{}}}"#,
+                location.latitude, location.longitude
+            )?,
+            None => json.push_str("null"),
+        }
+        json.push_str(r#","readings":["#);
+        for (idx, reading) in ev.readings.iter().enumerate() {
+            if idx > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                r#"{{"device_id":{},"reading_type":"{}","timestamp":{},"value":{}}}"#,
+                JsonId(&reading.device_id),
+                reading.reading_type.name(),
+                number(reading.timestamp),
+                reading.value
+            )?;
+        }
+        json.push_str("]}}");
+        Ok(())
+    }
+}
+
+fn number<N: UniqueSaturatedInto<u64>>(n: N) -> u64 {
+    n.unique_saturated_into()
+}
+
+// Bytes as a 0x-prefixed hex string
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0x")?;
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+// Hash as a JSON hex string, or `null` when unknown
+struct JsonHash<'a>(Option<&'a [u8]>);
+
+impl fmt::Display for JsonHash<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(hash) => write!(f, r#""{}""#, Hex(hash)),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+// Identifier as a JSON object tagged by its encoding: `{"utf8":"..."}` if printable,
+// `{"hex":"0x..."}` otherwise, so that printable identifiers starting with `0x` are not
+// mistaken for hex encoded ones
+struct JsonId<'a>(&'a [u8]);
+
+impl fmt::Display for JsonId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match core::str::from_utf8(self.0) {
+            Ok(id) if !id.chars().any(char::is_control) => {
+                f.write_str(r#"{"utf8":""#)?;
+                for c in id.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_char('\\')?;
+                    }
+                    f.write_char(c)?;
+                }
+                f.write_str(r#""}"#)
+            }
+            _ => write!(f, r#"{{"hex":"{}"}}"#, Hex(self.0)),
+        }
+    }
+}
@@ -1,13 +1,20 @@
 use super::*;
 use crate::{mock::*, types::*, Error};
 use fixed::types::I16F16;
-use frame_support::{assert_noop, assert_ok, dispatch};
+use frame_support::{
+    assert_noop, assert_ok, dispatch,
+    traits::{OnInitialize, OnRuntimeUpgrade},
+};
+use sp_core::{
+    offchain::{testing, OffchainExt},
+    sr25519, Pair,
+};
 
 pub fn store_test_shipment<T: Trait>(
     id: ShipmentId,
     owner: T::AccountId,
     status: ShipmentStatus,
-    products: Vec<ProductId>,
+    products: Vec<ShippedProduct>,
     registered: T::Moment,
 ) {
     Shipments::<T>::insert(
@@ -19,10 +26,18 @@ pub fn store_test_shipment<T: Trait>(
             products,
             registered,
             delivered: None,
+            itinerary: vec![],
         },
     );
 }
 
+pub fn shipped(product_id: &[u8]) -> ShippedProduct {
+    ShippedProduct {
+        product_id: product_id.to_vec(),
+        lot_id: None,
+    }
+}
+
 pub fn store_test_event<T: Trait>(shipment_id: ShipmentId, event_type: ShippingEventType) {
     let event = ShippingEvent {
         event_type,
@@ -41,6 +56,9 @@ const TEST_PRODUCT_ID: &str = "00012345678905";
 const TEST_SHIPMENT_ID: &str = "0001";
 const TEST_ORGANIZATION: &str = "Northwind";
 const TEST_SENDER: &str = "Alice";
+const TEST_OTHER_ORGANIZATION: &str = "Contoso";
+const TEST_OTHER_SENDER: &str = "Bob";
+const TEST_DEVICE_ID: &str = "14d453ea4bdf46bc8042";
 const LONG_VALUE : &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Donec aliquam ut tortor nec congue. Pellente";
 
 #[test]
@@ -57,6 +75,8 @@ fn register_shipment_without_products() {
             id.clone(),
             owner.clone(),
             vec![],
+            vec![],
+            vec![],
         );
 
         assert_ok!(result);
@@ -69,7 +89,8 @@ fn register_shipment_without_products() {
                 status: ShipmentStatus::Pending,
                 products: vec![],
                 registered: now,
-                delivered: None
+                delivered: None,
+                itinerary: vec![]
             })
         );
 
@@ -103,16 +124,21 @@ fn register_shipment_with_valid_products() {
         let owner = account_key(TEST_ORGANIZATION);
         let now = 42;
         Timestamp::set_timestamp(now);
+        for product_id in &[b"00012345600001", b"00012345600002", b"00012345600003"] {
+            MockProducts::store(product_id.to_vec(), owner, ProductStatus::Active);
+        }
 
         let result = ProductTracking::register_shipment(
             Origin::signed(sender),
             id.clone(),
             owner.clone(),
             vec![
-                b"00012345600001".to_vec(),
-                b"00012345600002".to_vec(),
-                b"00012345600003".to_vec(),
+                shipped(b"00012345600001"),
+                shipped(b"00012345600002"),
+                shipped(b"00012345600003"),
             ],
+            vec![],
+            vec![],
         );
 
         assert_ok!(result);
@@ -124,12 +150,13 @@ fn register_shipment_with_valid_products() {
                 owner: owner,
                 status: ShipmentStatus::Pending,
                 products: vec![
-                    b"00012345600001".to_vec(),
-                    b"00012345600002".to_vec(),
-                    b"00012345600003".to_vec(),
+                    shipped(b"00012345600001"),
+                    shipped(b"00012345600002"),
+                    shipped(b"00012345600003"),
                 ],
                 registered: now,
-                delivered: None
+                delivered: None,
+                itinerary: vec![]
             })
         );
 
@@ -163,7 +190,9 @@ fn register_shipment_with_invalid_sender() {
                 Origin::none(),
                 TEST_SHIPMENT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
-                vec!()
+                vec!(),
+                vec![],
+                vec![]
             ),
             dispatch::DispatchError::BadOrigin
         );
@@ -178,7 +207,9 @@ fn register_shipment_with_missing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 vec!(),
                 account_key(TEST_ORGANIZATION),
-                vec!()
+                vec!(),
+                vec![],
+                vec![]
             ),
             Error::<Test>::InvalidOrMissingIdentifier
         );
@@ -193,13 +224,49 @@ fn register_shipment_with_long_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 LONG_VALUE.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
-                vec!()
+                vec!(),
+                vec![],
+                vec![]
             ),
             Error::<Test>::InvalidOrMissingIdentifier
         );
     })
 }
 
+#[test]
+fn register_shipment_with_unknown_organization() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_SENDER),
+                vec!(),
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::OwnerNotOrganization
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_sender_not_member() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key("Bob")),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec!(),
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::SenderNotMemberOrDelegate
+        );
+    })
+}
+
 #[test]
 fn register_shipment_with_existing_id() {
     new_test_ext().execute_with(|| {
@@ -219,6 +286,8 @@ fn register_shipment_with_existing_id() {
                 Origin::signed(account_key(TEST_SENDER)),
                 existing_shipment,
                 account_key(TEST_ORGANIZATION),
+                vec![],
+                vec![],
                 vec![]
             ),
             Error::<Test>::ShipmentAlreadyExists
@@ -235,18 +304,20 @@ fn register_shipment_with_too_many_products() {
                 TEST_SHIPMENT_ID.as_bytes().to_owned(),
                 account_key(TEST_ORGANIZATION),
                 vec![
-                    b"00012345600001".to_vec(),
-                    b"00012345600002".to_vec(),
-                    b"00012345600003".to_vec(),
-                    b"00012345600004".to_vec(),
-                    b"00012345600005".to_vec(),
-                    b"00012345600006".to_vec(),
-                    b"00012345600007".to_vec(),
-                    b"00012345600008".to_vec(),
-                    b"00012345600009".to_vec(),
-                    b"00012345600010".to_vec(),
-                    b"00012345600011".to_vec(),
-                ]
+                    shipped(b"00012345600001"),
+                    shipped(b"00012345600002"),
+                    shipped(b"00012345600003"),
+                    shipped(b"00012345600004"),
+                    shipped(b"00012345600005"),
+                    shipped(b"00012345600006"),
+                    shipped(b"00012345600007"),
+                    shipped(b"00012345600008"),
+                    shipped(b"00012345600009"),
+                    shipped(b"00012345600010"),
+                    shipped(b"00012345600011"),
+                ],
+                vec![],
+                vec![]
             ),
             Error::<Test>::ShipmentHasTooManyProducts
         );
@@ -342,7 +413,7 @@ fn track_shipment_pickup() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::Pending,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -380,9 +451,10 @@ fn track_shipment_pickup() {
                 id: shipment_id.clone(),
                 owner: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
                 registered: now,
-                delivered: None
+                delivered: None,
+                itinerary: vec![]
             })
         );
 
@@ -410,7 +482,7 @@ fn track_shipment_delivery() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -450,9 +522,10 @@ fn track_shipment_delivery() {
                 id: shipment_id.clone(),
                 owner: owner,
                 status: ShipmentStatus::Delivered,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
                 registered: now,
-                delivered: Some(now)
+                delivered: Some(now),
+                itinerary: vec![]
             })
         );
 
@@ -479,7 +552,7 @@ fn track_shipment_for_delivered_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::Delivered,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -509,7 +582,7 @@ fn track_shipment_for_intransit_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -527,6 +600,167 @@ fn track_shipment_for_intransit_shipment() {
     })
 }
 
+#[test]
+fn track_shipment_delivery_for_pending_shipment() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ Pending status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Pending,
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+            now,
+        );
+
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentIsPending
+        );
+    })
+}
+
+#[test]
+fn track_shipment_cancellation() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ Pending status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::Pending,
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+            now,
+        );
+        store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
+
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Cancel,
+            now,
+            None,
+            None
+        ));
+
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|event| event.event_type),
+            Some(ShippingEventType::ShipmentCancel)
+        );
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|shipment| shipment.status),
+            Some(ShipmentStatus::Cancelled)
+        );
+
+        // Cancelled shipments cannot be picked up anymore
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Pickup,
+                now,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentHasBeenCancelled
+        );
+    })
+}
+
+#[test]
+fn track_shipment_rejection_and_return() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+
+        // Store shipment w/ InTransit status
+        store_test_shipment::<Test>(
+            shipment_id.clone(),
+            owner,
+            ShipmentStatus::InTransit,
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+            now,
+        );
+
+        // Shipment is damaged, then rejected at delivery & returned
+        for (operation, status) in vec![
+            (ShippingOperation::ReportDamage, ShipmentStatus::Damaged),
+            (ShippingOperation::Reject, ShipmentStatus::Rejected),
+            (ShippingOperation::Scan, ShipmentStatus::Rejected),
+            (ShippingOperation::Return, ShipmentStatus::Returned),
+        ] {
+            assert_ok!(ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                operation,
+                now,
+                None,
+                None
+            ));
+            assert_eq!(
+                ProductTracking::shipment_by_id(&shipment_id).map(|shipment| shipment.status),
+                Some(status)
+            );
+        }
+        assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2, 3, 4]);
+        assert_eq!(
+            AllEvents::<Test>::get(4).map(|event| event.event_type),
+            Some(ShippingEventType::ShipmentReturn)
+        );
+
+        // Returned shipments are not delivered anymore
+        assert_noop!(
+            ProductTracking::track_shipment(
+                Origin::signed(owner),
+                shipment_id.clone(),
+                ShippingOperation::Deliver,
+                now,
+                None,
+                None
+            ),
+            Error::<Test>::ShipmentHasBeenReturned
+        );
+    })
+}
+
+#[test]
+fn track_shipment_invalid_transitions() {
+    new_test_ext().execute_with(|| {
+        use ShipmentStatus::*;
+        use ShippingOperation::*;
+
+        for (status, operation, error) in vec![
+            (Pending, Scan, Error::<Test>::ShipmentIsPending),
+            (Pending, Reject, Error::<Test>::ShipmentIsPending),
+            (InTransit, Cancel, Error::<Test>::ShipmentIsInTransit),
+            (InTransit, Return, Error::<Test>::ShipmentIsInTransit),
+            (Damaged, Pickup, Error::<Test>::ShipmentIsDamaged),
+            (Rejected, Deliver, Error::<Test>::ShipmentHasBeenRejected),
+            (Delivered, Reject, Error::<Test>::ShipmentHasBeenDelivered),
+            (Lost, Scan, Error::<Test>::ShipmentHasBeenLost),
+        ] {
+            assert_eq!(
+                ProductTracking::next_status(&status, &operation).map_err(|e| e.as_str()),
+                Err(error.as_str())
+            );
+        }
+    })
+}
+
 #[test]
 fn monitor_shipment() {
     new_test_ext().execute_with(|| {
@@ -539,7 +773,7 @@ fn monitor_shipment() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -547,6 +781,8 @@ fn monitor_shipment() {
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentPickup);
 
+        register_test_device();
+
         // Define location & readings for sensor reading
         let location = ReadPoint {
             latitude: I16F16::from_num(52.4941126),
@@ -567,7 +803,7 @@ fn monitor_shipment() {
             ShippingOperation::Scan,
             now,
             Some(location.clone()),
-            Some(readings.clone())
+            Some(sign_readings(&shipment_id, &readings))
         ));
 
         // Storage is correctly updated
@@ -591,9 +827,10 @@ fn monitor_shipment() {
                 id: shipment_id.clone(),
                 owner: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
                 registered: now,
-                delivered: None
+                delivered: None,
+                itinerary: vec![]
             })
         );
     })
@@ -611,7 +848,7 @@ fn monitor_shipment_with_negative_latlon() {
             shipment_id.clone(),
             owner,
             ShipmentStatus::InTransit,
-            vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
             now,
         );
 
@@ -619,6 +856,8 @@ fn monitor_shipment_with_negative_latlon() {
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentRegistration);
         store_test_event::<Test>(shipment_id.clone(), ShippingEventType::ShipmentPickup);
 
+        register_test_device();
+
         // Define location & readings for sensor reading
         let location = ReadPoint {
             // Rio de Janeiro, Brazil
@@ -640,7 +879,7 @@ fn monitor_shipment_with_negative_latlon() {
             ShippingOperation::Scan,
             now,
             Some(location.clone()),
-            Some(readings.clone())
+            Some(sign_readings(&shipment_id, &readings))
         ));
 
         // Storage is correctly updated
@@ -664,10 +903,1811 @@ fn monitor_shipment_with_negative_latlon() {
                 id: shipment_id.clone(),
                 owner: owner,
                 status: ShipmentStatus::InTransit,
-                products: vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
                 registered: now,
-                delivered: None
+                delivered: None,
+                itinerary: vec![]
             })
         );
     })
 }
+
+#[test]
+fn register_shipment_with_discontinued_product() {
+    new_test_ext().execute_with(|| {
+        MockProducts::store(
+            TEST_PRODUCT_ID.as_bytes().to_owned(),
+            account_key(TEST_ORGANIZATION),
+            ProductStatus::Discontinued,
+        );
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasDiscontinuedProduct
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_recalled_product() {
+    new_test_ext().execute_with(|| {
+        MockProducts::store(
+            TEST_PRODUCT_ID.as_bytes().to_owned(),
+            account_key(TEST_ORGANIZATION),
+            ProductStatus::Recalled,
+        );
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasRecalledProduct
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_unknown_product() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasUnknownProduct
+        );
+    })
+}
+
+#[test]
+fn register_shipment_with_product_of_supplier() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let supplier = account_key(TEST_OTHER_ORGANIZATION);
+        MockProducts::enforce_ownership(true);
+        MockProducts::store(TEST_PRODUCT_ID.as_bytes().to_owned(), supplier, ProductStatus::Active);
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                owner,
+                vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasProductOfUnknownSupplier
+        );
+
+        assert_ok!(ProductTracking::add_supplier(
+            Origin::signed(account_key(TEST_SENDER)),
+            owner,
+            supplier
+        ));
+        assert_eq!(ProductTracking::suppliers_of(&owner), vec![supplier]);
+
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(account_key(TEST_SENDER)),
+            TEST_SHIPMENT_ID.as_bytes().to_owned(),
+            owner,
+            vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+            vec![],
+            vec![]
+        ));
+    })
+}
+
+#[test]
+fn remove_unknown_supplier() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            ProductTracking::remove_supplier(
+                Origin::signed(account_key(TEST_SENDER)),
+                account_key(TEST_ORGANIZATION),
+                account_key(TEST_OTHER_ORGANIZATION)
+            ),
+            Error::<Test>::SupplierIsUnknown
+        );
+    })
+}
+
+#[test]
+fn recall_product_flags_shipments() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let now = 42;
+        Timestamp::set_timestamp(now);
+
+        MockProducts::store(product_id.clone(), owner, ProductStatus::Active);
+
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(sender),
+            shipment_id.clone(),
+            owner,
+            vec![shipped(&product_id)],
+            vec![],
+            vec![],
+        ));
+        assert_eq!(
+            ProductTracking::shipments_of_product(&product_id),
+            vec![shipment_id.clone()]
+        );
+
+        // Product registry notifies the recall, the shipments being flagged from the next block on
+        ProductTracking::on_product_recall(&product_id);
+        assert_eq!(ProductTracking::recalls_of_shipment(&shipment_id), Vec::<ProductId>::new());
+        Timestamp::set_timestamp(now + 6_000);
+        ProductTracking::on_initialize(2);
+
+        // Shipment is flagged & recall event is stored, at the time of the recall
+        assert_eq!(
+            ProductTracking::recalls_of_shipment(&shipment_id),
+            vec![product_id.clone()]
+        );
+        assert_eq!(EventCount::get(), 2);
+        assert_eq!(
+            AllEvents::<Test>::get(2),
+            Some(ShippingEvent {
+                event_type: ShippingEventType::ShipmentRecall,
+                shipment_id: shipment_id.clone(),
+                location: None,
+                readings: vec![],
+                timestamp: now,
+            })
+        );
+        assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2]);
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentRecalled(
+                shipment_id.clone(),
+                product_id.clone(),
+                2
+            ))));
+    })
+}
+
+#[test]
+fn recall_product_flags_shipments_over_blocks() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let product_id = TEST_PRODUCT_ID.as_bytes().to_owned();
+        let shipment_ids: Vec<ShipmentId> = (1..=3).map(|n| format!("{:04}", n).into_bytes()).collect();
+
+        MockProducts::store(product_id.clone(), owner, ProductStatus::Active);
+        for shipment_id in shipment_ids.iter() {
+            assert_ok!(ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                shipment_id.clone(),
+                owner,
+                vec![shipped(&product_id)],
+                vec![],
+                vec![],
+            ));
+        }
+        let flagged = || {
+            shipment_ids
+                .iter()
+                .filter(|shipment_id| !ProductTracking::recalls_of_shipment(shipment_id).is_empty())
+                .count()
+        };
+
+        // The recall itself has a constant weight
+        ProductTracking::on_product_recall(&product_id);
+        assert_eq!(
+            ProductTracking::on_product_recall_weight(&product_id),
+            <Test as system::Trait>::DbWeight::get().writes(1)
+        );
+        assert_eq!(flagged(), 0);
+
+        // At most 2 shipments flagged per block
+        ProductTracking::on_initialize(2);
+        assert_eq!(flagged(), 2);
+        assert_eq!(ProductTracking::pending_recalls(), vec![(product_id.clone(), 0, 2)]);
+
+        ProductTracking::on_initialize(3);
+        assert_eq!(flagged(), 3);
+        assert_eq!(ProductTracking::pending_recalls(), vec![]);
+
+        // Each shipment is flagged once
+        ProductTracking::on_initialize(4);
+        assert_eq!(EventCount::get(), 3 + 3);
+    })
+}
+
+#[test]
+fn register_shipment_with_product_lots() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+        let now = 42;
+        Timestamp::set_timestamp(now);
+        MockProducts::store(TEST_PRODUCT_ID.as_bytes().to_owned(), owner, ProductStatus::Active);
+        MockProducts::store_lot(TEST_PRODUCT_ID.as_bytes(), b"L20200901");
+        MockProducts::store_lot(TEST_PRODUCT_ID.as_bytes(), b"L20200902");
+
+        let products = vec![
+            ShippedProduct {
+                product_id: TEST_PRODUCT_ID.as_bytes().to_owned(),
+                lot_id: Some(b"L20200901".to_vec()),
+            },
+            ShippedProduct {
+                product_id: TEST_PRODUCT_ID.as_bytes().to_owned(),
+                lot_id: Some(b"L20200902".to_vec()),
+            },
+        ];
+
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(sender),
+            id.clone(),
+            owner.clone(),
+            products.clone(),
+            vec![],
+            vec![],
+        ));
+
+        assert_eq!(
+            ProductTracking::shipment_by_id(&id),
+            Some(Shipment {
+                id: id.clone(),
+                owner: owner,
+                status: ShipmentStatus::Pending,
+                products: products,
+                registered: now,
+                delivered: None,
+                itinerary: vec![]
+            })
+        );
+        // Shipment is indexed once per product
+        assert_eq!(
+            ProductTracking::shipments_of_product(TEST_PRODUCT_ID.as_bytes().to_owned()),
+            vec![id]
+        );
+    });
+}
+
+#[test]
+fn register_shipment_with_unknown_lot() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        MockProducts::store(TEST_PRODUCT_ID.as_bytes().to_owned(), owner, ProductStatus::Active);
+        MockProducts::store_lot(b"00012345600029", b"L20200901");
+
+        // Lot of another product
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                owner,
+                vec![ShippedProduct {
+                    product_id: TEST_PRODUCT_ID.as_bytes().to_owned(),
+                    lot_id: Some(b"L20200901".to_vec()),
+                }],
+                vec![],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasUnknownLot
+        );
+    });
+}
+
+#[test]
+fn migrate_shipments_from_v1() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        // Store a shipment w/ plain product IDs
+        frame_support::storage::unhashed::put(
+            &Shipments::<Test>::hashed_key_for(&id),
+            &(
+                id.clone(),
+                owner,
+                ShipmentStatus::InTransit,
+                vec![TEST_PRODUCT_ID.as_bytes().to_owned()],
+                42u64,
+                Option::<u64>::None,
+            ),
+        );
+        StorageVersion::put(Releases::V1_0_0);
+
+        ProductTracking::on_runtime_upgrade();
+
+        assert_eq!(StorageVersion::get(), Releases::V4_0_0);
+        assert_eq!(
+            ProductTracking::shipment_by_id(&id),
+            Some(Shipment {
+                id: id.clone(),
+                owner: owner,
+                status: ShipmentStatus::InTransit,
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                registered: 42,
+                delivered: None,
+                itinerary: vec![]
+            })
+        );
+        // Shipments registered before the upgrade are indexed by product, for recalls
+        assert_eq!(
+            ProductTracking::shipments_of_product(TEST_PRODUCT_ID.as_bytes().to_vec()),
+            vec![id.clone()]
+        );
+        ProductTracking::on_product_recall(&TEST_PRODUCT_ID.as_bytes().to_vec());
+        ProductTracking::on_initialize(2);
+        assert_eq!(
+            ProductTracking::recalls_of_shipment(&id),
+            vec![TEST_PRODUCT_ID.as_bytes().to_vec()]
+        );
+    })
+}
+
+fn register_test_shipment() -> ShipmentId {
+    let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+    assert_ok!(ProductTracking::register_shipment(
+        Origin::signed(account_key(TEST_SENDER)),
+        shipment_id.clone(),
+        account_key(TEST_ORGANIZATION),
+        vec![],
+        vec![],
+        vec![]
+    ));
+    shipment_id
+}
+
+#[test]
+fn custody_handover() {
+    new_test_ext().execute_with(|| {
+        let org = account_key(TEST_ORGANIZATION);
+        let other_org = account_key(TEST_OTHER_ORGANIZATION);
+        let shipment_id = register_test_shipment();
+
+        // Owner is the first custodian
+        assert_eq!(ProductTracking::custodian_of(&shipment_id), Some(org));
+
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            other_org
+        ));
+        assert_eq!(
+            ProductTracking::handover_of(&shipment_id),
+            Some(CustodyHandover {
+                from: org,
+                to: other_org,
+                expires: 11
+            })
+        );
+        // Custody doesn't move until the handover is accepted
+        assert_eq!(ProductTracking::custodian_of(&shipment_id), Some(org));
+
+        assert_ok!(ProductTracking::accept_handover(
+            Origin::signed(account_key(TEST_OTHER_SENDER)),
+            shipment_id.clone()
+        ));
+        assert_eq!(ProductTracking::custodian_of(&shipment_id), Some(other_org));
+        assert_eq!(ProductTracking::handover_of(&shipment_id), None);
+
+        // Each step is recorded as a shipping event
+        assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2, 3]);
+        assert_eq!(
+            AllEvents::<Test>::get(2).map(|event| event.event_type),
+            Some(ShippingEventType::ShipmentHandoverPropose)
+        );
+        assert_eq!(
+            AllEvents::<Test>::get(3).map(|event| event.event_type),
+            Some(ShippingEventType::ShipmentHandoverAccept)
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::CustodyTransferred(
+                account_key(TEST_OTHER_SENDER),
+                shipment_id.clone(),
+                org,
+                other_org
+            ))));
+    })
+}
+
+#[test]
+fn custody_handover_declined() {
+    new_test_ext().execute_with(|| {
+        let org = account_key(TEST_ORGANIZATION);
+        let shipment_id = register_test_shipment();
+
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+        assert_ok!(ProductTracking::decline_handover(
+            Origin::signed(account_key(TEST_OTHER_SENDER)),
+            shipment_id.clone()
+        ));
+
+        assert_eq!(ProductTracking::custodian_of(&shipment_id), Some(org));
+        assert_eq!(ProductTracking::handover_of(&shipment_id), None);
+        assert_eq!(
+            AllEvents::<Test>::get(3).map(|event| event.event_type),
+            Some(ShippingEventType::ShipmentHandoverDecline)
+        );
+    })
+}
+
+#[test]
+fn custody_handover_not_proposed_by_custodian() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_test_shipment();
+
+        assert_noop!(
+            ProductTracking::propose_handover(
+                Origin::signed(account_key(TEST_OTHER_SENDER)),
+                shipment_id,
+                account_key(TEST_OTHER_ORGANIZATION)
+            ),
+            Error::<Test>::SenderNotCustodian
+        );
+    })
+}
+
+#[test]
+fn custody_handover_already_pending() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_test_shipment();
+
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+        assert_noop!(
+            ProductTracking::propose_handover(
+                Origin::signed(account_key(TEST_SENDER)),
+                shipment_id.clone(),
+                account_key(TEST_OTHER_ORGANIZATION)
+            ),
+            Error::<Test>::HandoverAlreadyPending
+        );
+
+        // Expired handovers can be replaced
+        System::set_block_number(12);
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id,
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+    })
+}
+
+#[test]
+fn custody_handover_accepted_by_other_org() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_test_shipment();
+
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+        assert_noop!(
+            ProductTracking::accept_handover(Origin::signed(account_key(TEST_SENDER)), shipment_id),
+            Error::<Test>::SenderNotHandoverRecipient
+        );
+    })
+}
+
+#[test]
+fn custody_handover_accepted_after_timeout() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_test_shipment();
+
+        assert_ok!(ProductTracking::propose_handover(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION)
+        ));
+
+        System::set_block_number(12);
+        assert_noop!(
+            ProductTracking::accept_handover(
+                Origin::signed(account_key(TEST_OTHER_SENDER)),
+                shipment_id
+            ),
+            Error::<Test>::HandoverHasExpired
+        );
+    })
+}
+
+fn device_pair() -> sr25519::Pair {
+    sr25519::Pair::from_string("//Sensor", None).expect("static values are valid; qed")
+}
+
+fn register_test_device() {
+    assert_ok!(ProductTracking::register_device(
+        Origin::signed(account_key(TEST_SENDER)),
+        TEST_DEVICE_ID.as_bytes().to_owned(),
+        account_key(TEST_ORGANIZATION),
+        device_pair().public().into(),
+        b"DataLogger".to_vec(),
+        vec![ReadingType::Humidity, ReadingType::Temperature]
+    ));
+}
+
+fn sign_readings(shipment_id: &ShipmentId, readings: &[Reading<u64>]) -> Vec<SignedReading<u64>> {
+    readings
+        .iter()
+        .map(|reading| {
+            let payload = (shipment_id, &reading.reading_type, &reading.timestamp, &reading.value).encode();
+            SignedReading {
+                reading: reading.clone(),
+                signature: device_pair().sign(&payload).into(),
+            }
+        })
+        .collect()
+}
+
+fn temperature(value: f64, timestamp: u64) -> Reading<u64> {
+    Reading {
+        device_id: "14d453ea4bdf46bc8042".as_bytes().to_owned(),
+        reading_type: ReadingType::Temperature,
+        value: I16F16::from_num(value),
+        timestamp,
+    }
+}
+
+fn register_monitored_shipment(max_excursion: Option<u64>) -> ShipmentId {
+    let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+    let owner = account_key(TEST_ORGANIZATION);
+    register_test_device();
+
+    // Cold chain shipment, to be kept between 2°C & 8°C
+    assert_ok!(ProductTracking::register_shipment(
+        Origin::signed(account_key(TEST_SENDER)),
+        shipment_id.clone(),
+        owner,
+        vec![],
+        vec![ConditionThreshold {
+            reading_type: ReadingType::Temperature,
+            min: Some(I16F16::from_num(2)),
+            max: Some(I16F16::from_num(8)),
+            max_excursion,
+        }],
+        vec![]
+    ));
+    assert_ok!(ProductTracking::track_shipment(
+        Origin::signed(owner),
+        shipment_id.clone(),
+        ShippingOperation::Pickup,
+        0,
+        None,
+        None
+    ));
+    shipment_id
+}
+
+fn scan_readings(shipment_id: &ShipmentId, readings: Vec<Reading<u64>>) {
+    assert_ok!(ProductTracking::track_shipment(
+        Origin::signed(account_key(TEST_ORGANIZATION)),
+        shipment_id.clone(),
+        ShippingOperation::Scan,
+        readings.last().map_or(0, |reading| reading.timestamp),
+        None,
+        Some(sign_readings(shipment_id, &readings))
+    ));
+}
+
+#[test]
+fn register_shipment_with_invalid_thresholds() {
+    new_test_ext().execute_with(|| {
+        let threshold = ConditionThreshold {
+            reading_type: ReadingType::Temperature,
+            min: Some(I16F16::from_num(8)),
+            max: Some(I16F16::from_num(2)),
+            max_excursion: None,
+        };
+
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![],
+                vec![threshold.clone()],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasInvalidThreshold
+        );
+
+        let threshold = ConditionThreshold {
+            min: None,
+            ..threshold
+        };
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![],
+                vec![threshold.clone(), threshold],
+                vec![]
+            ),
+            Error::<Test>::ShipmentHasDuplicateThreshold
+        );
+    })
+}
+
+#[test]
+fn monitor_shipment_within_thresholds() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(None);
+
+        scan_readings(&shipment_id, vec![temperature(4.5, 10), temperature(7.9, 20)]);
+
+        assert!(!ProductTracking::is_compromised(&shipment_id));
+        assert_eq!(ProductTracking::breaches_of(&shipment_id), vec![]);
+    })
+}
+
+#[test]
+fn monitor_shipment_with_breached_threshold() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(None);
+
+        scan_readings(&shipment_id, vec![temperature(4.5, 10), temperature(9.5, 20)]);
+
+        // Any out of range reading is a breach
+        assert!(ProductTracking::is_compromised(&shipment_id));
+        assert_eq!(
+            ProductTracking::breaches_of(&shipment_id),
+            vec![temperature(9.5, 20)]
+        );
+
+        // Event is raised
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentConditionBreached(
+                shipment_id.clone(),
+                temperature(9.5, 20)
+            ))));
+    })
+}
+
+#[test]
+fn monitor_shipment_with_cumulative_excursion() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(Some(30));
+
+        // 20 time units out of range, within the allowed excursion
+        scan_readings(&shipment_id, vec![temperature(9.5, 10), temperature(5.0, 30)]);
+        assert!(!ProductTracking::is_compromised(&shipment_id));
+
+        // 10 more time units out of range, still within the allowed excursion
+        scan_readings(&shipment_id, vec![temperature(1.5, 40), temperature(1.0, 50)]);
+        assert!(!ProductTracking::is_compromised(&shipment_id));
+        assert_eq!(
+            ProductTracking::excursion_of(&shipment_id, ReadingType::Temperature),
+            Excursion {
+                last_reading: 50,
+                out_of_range: true,
+                duration: 30,
+                breached: false,
+            }
+        );
+
+        // Allowed excursion is exceeded
+        scan_readings(&shipment_id, vec![temperature(1.0, 55)]);
+        assert!(ProductTracking::is_compromised(&shipment_id));
+        assert_eq!(
+            ProductTracking::breaches_of(&shipment_id),
+            vec![temperature(1.0, 55)]
+        );
+    })
+}
+
+#[test]
+fn monitor_shipment_with_excursion_exceeded_when_ended() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(Some(30));
+        let breaches = || {
+            System::events()
+                .into_iter()
+                .filter(|er| match er.event {
+                    TestEvent::product_tracking(RawEvent::ShipmentConditionBreached(..)) => true,
+                    _ => false,
+                })
+                .count()
+        };
+
+        // Out of range, the excursion only being measured by the next reading
+        scan_readings(&shipment_id, vec![temperature(9.5, 10)]);
+        assert!(!ProductTracking::is_compromised(&shipment_id));
+
+        // The reading ending the excursion reports its breach
+        scan_readings(&shipment_id, vec![temperature(5.0, 41)]);
+        assert!(ProductTracking::is_compromised(&shipment_id));
+        assert_eq!(
+            ProductTracking::breaches_of(&shipment_id),
+            vec![temperature(5.0, 41)]
+        );
+        assert_eq!(breaches(), 1);
+
+        // Once only
+        scan_readings(&shipment_id, vec![temperature(4.0, 50)]);
+        assert_eq!(breaches(), 1);
+
+        // A new excursion, beyond the cumulative allowance, is reported once as well
+        scan_readings(&shipment_id, vec![temperature(9.5, 60), temperature(9.8, 70)]);
+        assert_eq!(
+            ProductTracking::breaches_of(&shipment_id),
+            vec![temperature(5.0, 41), temperature(9.5, 60)]
+        );
+        assert_eq!(breaches(), 2);
+    })
+}
+
+#[test]
+fn migrate_excursions_from_v3() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(Some(30));
+
+        // Store excursions w/o breach flag, beyond & within the allowance
+        let store_excursion = |reading_type: ReadingType, duration: u64| {
+            frame_support::storage::unhashed::put(
+                &Excursions::<Test>::hashed_key_for(&shipment_id, reading_type),
+                &(50u64, true, duration),
+            )
+        };
+        store_excursion(ReadingType::Temperature, 40);
+        store_excursion(ReadingType::Humidity, 40);
+        StorageVersion::put(Releases::V3_0_0);
+
+        ProductTracking::on_runtime_upgrade();
+
+        assert_eq!(StorageVersion::get(), Releases::V4_0_0);
+        assert_eq!(
+            ProductTracking::excursion_of(&shipment_id, ReadingType::Temperature),
+            Excursion {
+                last_reading: 50,
+                out_of_range: true,
+                duration: 40,
+                breached: true,
+            }
+        );
+        // No threshold for humidity
+        assert!(!ProductTracking::excursion_of(&shipment_id, ReadingType::Humidity).breached);
+    })
+}
+
+fn point(latitude: f64, longitude: f64) -> ReadPoint {
+    ReadPoint {
+        latitude: I16F16::from_num(latitude),
+        longitude: I16F16::from_num(longitude),
+    }
+}
+
+fn leg(
+    origin: ReadPoint,
+    destination: ReadPoint,
+    departure: u64,
+    arrival: u64,
+    carrier: &str,
+) -> Leg<sr25519::Public, u64> {
+    Leg {
+        origin,
+        destination,
+        departure,
+        arrival,
+        carrier: account_key(carrier),
+        completed: None,
+    }
+}
+
+fn scan_at(shipment_id: &ShipmentId, location: ReadPoint, timestamp: u64) {
+    assert_ok!(ProductTracking::track_shipment(
+        Origin::signed(account_key(TEST_ORGANIZATION)),
+        shipment_id.clone(),
+        ShippingOperation::Scan,
+        timestamp,
+        Some(location),
+        None
+    ));
+}
+
+#[test]
+fn register_shipment_with_invalid_itinerary() {
+    new_test_ext().execute_with(|| {
+        // Legs are not consecutive
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![],
+                vec![],
+                vec![
+                    leg(point(0.0, 0.0), point(1.0, 0.0), 0, 100, TEST_ORGANIZATION),
+                    leg(point(2.0, 0.0), point(3.0, 0.0), 100, 200, TEST_ORGANIZATION),
+                ]
+            ),
+            Error::<Test>::ShipmentHasInvalidItinerary
+        );
+
+        // Carrier is not an organization
+        assert_noop!(
+            ProductTracking::register_shipment(
+                Origin::signed(account_key(TEST_SENDER)),
+                TEST_SHIPMENT_ID.as_bytes().to_owned(),
+                account_key(TEST_ORGANIZATION),
+                vec![],
+                vec![],
+                vec![leg(point(0.0, 0.0), point(1.0, 0.0), 0, 100, TEST_SENDER)]
+            ),
+            Error::<Test>::ShipmentHasUnknownCarrier
+        );
+    })
+}
+
+#[test]
+fn follow_shipment_itinerary() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let itinerary = vec![
+            leg(point(0.0, 0.0), point(1.0, 0.0), 0, 100, TEST_ORGANIZATION),
+            leg(point(1.0, 0.0), point(1.0, 1.0), 100, 200, TEST_OTHER_ORGANIZATION),
+        ];
+
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            owner,
+            vec![],
+            vec![],
+            itinerary.clone()
+        ));
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Pickup,
+            0,
+            None,
+            None
+        ));
+
+        // On route, within the planned time window
+        scan_at(&shipment_id, point(0.5, 0.001), 50);
+        // Off route (~11km away from the route)
+        scan_at(&shipment_id, point(0.5, 0.1), 60);
+        // At destination of the first leg
+        scan_at(&shipment_id, point(1.0, 0.0), 100);
+        // On route of the second leg, but late
+        scan_at(&shipment_id, point(1.0, 0.5), 500);
+
+        let deviations: Vec<_> = System::events()
+            .into_iter()
+            .filter_map(|er| match er.event {
+                TestEvent::product_tracking(RawEvent::ShipmentDeviated(_, idx, deviation)) => {
+                    Some((idx, deviation))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            deviations,
+            vec![(0, Deviation::OffRoute), (1, Deviation::OutsideTimeWindow)]
+        );
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ShipmentLegCompleted(
+                shipment_id.clone(),
+                0
+            ))));
+
+        // First leg is completed
+        assert_eq!(
+            ProductTracking::shipment_by_id(&shipment_id).map(|shipment| shipment.itinerary),
+            Some(vec![
+                Leg {
+                    completed: Some(100),
+                    ..itinerary[0].clone()
+                },
+                itinerary[1].clone(),
+            ])
+        );
+    })
+}
+
+#[test]
+fn follow_shipment_itinerary_at_high_latitude() {
+    new_test_ext().execute_with(|| {
+        let owner = account_key(TEST_ORGANIZATION);
+        let shipment_id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+
+        assert_ok!(ProductTracking::register_shipment(
+            Origin::signed(account_key(TEST_SENDER)),
+            shipment_id.clone(),
+            owner,
+            vec![],
+            vec![],
+            vec![leg(point(60.0, 10.0), point(61.0, 10.0), 0, 100, TEST_ORGANIZATION)]
+        ));
+        assert_ok!(ProductTracking::track_shipment(
+            Origin::signed(owner),
+            shipment_id.clone(),
+            ShippingOperation::Pickup,
+            0,
+            None,
+            None
+        ));
+
+        // A degree of longitude is ~55km long at 60.5°N
+        // On route (~835m east of the route)
+        scan_at(&shipment_id, point(60.5, 10.015), 50);
+        // Off route (~1.4km east of the route)
+        scan_at(&shipment_id, point(60.5, 10.025), 60);
+
+        let deviations: Vec<_> = System::events()
+            .into_iter()
+            .filter_map(|er| match er.event {
+                TestEvent::product_tracking(RawEvent::ShipmentDeviated(_, idx, deviation)) => {
+                    Some((idx, deviation))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deviations, vec![(0, Deviation::OffRoute)]);
+    })
+}
+
+#[test]
+fn migrate_shipments_from_v2() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_SHIPMENT_ID.as_bytes().to_owned();
+        let owner = account_key(TEST_ORGANIZATION);
+
+        // Store a shipment w/o itinerary
+        frame_support::storage::unhashed::put(
+            &Shipments::<Test>::hashed_key_for(&id),
+            &(
+                id.clone(),
+                owner,
+                ShipmentStatus::Delivered,
+                vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                42u64,
+                Some(84u64),
+            ),
+        );
+        StorageVersion::put(Releases::V2_0_0);
+
+        ProductTracking::on_runtime_upgrade();
+
+        assert_eq!(StorageVersion::get(), Releases::V4_0_0);
+        assert_eq!(
+            ProductTracking::shipment_by_id(&id),
+            Some(Shipment {
+                id: id.clone(),
+                owner: owner,
+                status: ShipmentStatus::Delivered,
+                products: vec![shipped(TEST_PRODUCT_ID.as_bytes())],
+                registered: 42,
+                delivered: Some(84),
+                itinerary: vec![],
+            })
+        );
+    })
+}
+
+#[test]
+fn register_device() {
+    new_test_ext().execute_with(|| {
+        let sender = account_key(TEST_SENDER);
+        let owner = account_key(TEST_ORGANIZATION);
+        let id = TEST_DEVICE_ID.as_bytes().to_owned();
+
+        register_test_device();
+
+        assert_eq!(
+            ProductTracking::device_by_id(&id),
+            Some(Device {
+                id: id.clone(),
+                owner,
+                key: device_pair().public().into(),
+                device_type: b"DataLogger".to_vec(),
+                reading_types: vec![ReadingType::Humidity, ReadingType::Temperature],
+                active: true,
+            })
+        );
+        assert_eq!(ProductTracking::devices_of_org(owner), vec![id.clone()]);
+
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeviceRegistered(
+                sender,
+                id.clone(),
+                owner
+            ))));
+
+        // Device IDs are unique
+        assert_noop!(
+            ProductTracking::register_device(
+                Origin::signed(sender),
+                id.clone(),
+                owner,
+                device_pair().public().into(),
+                b"DataLogger".to_vec(),
+                vec![ReadingType::Temperature]
+            ),
+            Error::<Test>::DeviceAlreadyExists
+        );
+    })
+}
+
+#[test]
+fn register_device_with_invalid_reading_types() {
+    new_test_ext().execute_with(|| {
+        for reading_types in vec![vec![], vec![ReadingType::Shock, ReadingType::Shock]] {
+            assert_noop!(
+                ProductTracking::register_device(
+                    Origin::signed(account_key(TEST_SENDER)),
+                    TEST_DEVICE_ID.as_bytes().to_owned(),
+                    account_key(TEST_ORGANIZATION),
+                    device_pair().public().into(),
+                    b"DataLogger".to_vec(),
+                    reading_types
+                ),
+                Error::<Test>::DeviceHasInvalidReadingTypes
+            );
+        }
+    })
+}
+
+#[test]
+fn update_device_status() {
+    new_test_ext().execute_with(|| {
+        let id = TEST_DEVICE_ID.as_bytes().to_owned();
+        register_test_device();
+
+        // Only the device owner can update its status
+        assert_noop!(
+            ProductTracking::update_device_status(
+                Origin::signed(account_key(TEST_OTHER_SENDER)),
+                id.clone(),
+                false
+            ),
+            Error::<Test>::SenderNotMemberOrDelegate
+        );
+
+        assert_ok!(ProductTracking::update_device_status(
+            Origin::signed(account_key(TEST_SENDER)),
+            id.clone(),
+            false
+        ));
+        assert_eq!(ProductTracking::device_by_id(&id).map(|d| d.active), Some(false));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::DeviceStatusUpdated(
+                account_key(TEST_SENDER),
+                id.clone(),
+                false
+            ))));
+    })
+}
+
+#[test]
+fn track_shipment_with_invalid_readings() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_test_shipment();
+        register_test_device();
+
+        let track = |readings: Vec<SignedReading<u64>>| {
+            ProductTracking::track_shipment(
+                Origin::signed(account_key(TEST_ORGANIZATION)),
+                shipment_id.clone(),
+                ShippingOperation::Pickup,
+                10,
+                None,
+                Some(readings),
+            )
+        };
+
+        // Readings from unknown devices
+        let mut readings = sign_readings(&shipment_id, &[temperature(4.5, 10)]);
+        readings[0].reading.device_id = b"unknown".to_vec();
+        assert_noop!(track(readings), Error::<Test>::DeviceIsUnknown);
+
+        // Readings of a type not supported by the device
+        let mut reading = temperature(4.5, 10);
+        reading.reading_type = ReadingType::Tilt;
+        assert_noop!(
+            track(sign_readings(&shipment_id, &[reading])),
+            Error::<Test>::ReadingTypeNotSupportedByDevice
+        );
+
+        // Tampered readings, or readings signed for another shipment
+        let mut readings = sign_readings(&shipment_id, &[temperature(4.5, 10)]);
+        readings[0].reading.value = I16F16::from_num(3.5);
+        assert_noop!(track(readings), Error::<Test>::ReadingHasInvalidSignature);
+        assert_noop!(
+            track(sign_readings(&b"0002".to_vec(), &[temperature(4.5, 10)])),
+            Error::<Test>::ReadingHasInvalidSignature
+        );
+
+        // Readings from inactive devices
+        assert_ok!(ProductTracking::update_device_status(
+            Origin::signed(account_key(TEST_SENDER)),
+            TEST_DEVICE_ID.as_bytes().to_owned(),
+            false
+        ));
+        assert_noop!(
+            track(sign_readings(&shipment_id, &[temperature(4.5, 10)])),
+            Error::<Test>::DeviceIsInactive
+        );
+    })
+}
+
+fn signed_payload(nonce: u64, readings: Vec<Reading<u64>>) -> (ReadingsPayload<u64>, MultiSignature) {
+    let payload = ReadingsPayload {
+        device_id: TEST_DEVICE_ID.as_bytes().to_owned(),
+        nonce,
+        shipment_id: TEST_SHIPMENT_ID.as_bytes().to_owned(),
+        readings,
+    };
+    let signature = device_pair().sign(&payload.encode()).into();
+    (payload, signature)
+}
+
+fn validate_submission(payload: &ReadingsPayload<u64>, signature: &MultiSignature) -> TransactionValidity {
+    ProductTracking::validate_unsigned(
+        TransactionSource::External,
+        &Call::submit_readings(payload.clone(), signature.clone()),
+    )
+}
+
+#[test]
+fn submit_readings() {
+    new_test_ext().execute_with(|| {
+        let shipment_id = register_monitored_shipment(None);
+        let device_id = TEST_DEVICE_ID.as_bytes().to_owned();
+        let (payload, signature) = signed_payload(0, vec![temperature(9.5, 10)]);
+
+        let valid_tx = validate_submission(&payload, &signature).unwrap();
+        assert_eq!(valid_tx.priority, 100);
+        assert_eq!(valid_tx.longevity, 5);
+        assert!(valid_tx.requires.is_empty());
+
+        assert_ok!(ProductTracking::submit_readings(
+            Origin::none(),
+            payload.clone(),
+            signature.clone()
+        ));
+
+        // Readings are recorded as a scan of the shipment, & monitored
+        assert_eq!(ProductTracking::device_nonce(&device_id), 1);
+        assert_eq!(EventsOfShipment::get(&shipment_id), vec![1, 2, 3]);
+        assert_eq!(
+            AllEvents::<Test>::get(3).map(|ev| (ev.event_type, ev.readings)),
+            Some((ShippingEventType::ShipmentScan, vec![temperature(9.5, 10)]))
+        );
+        assert!(ProductTracking::is_compromised(&shipment_id));
+        assert!(System::events().iter().any(|er| er.event
+            == TestEvent::product_tracking(RawEvent::ReadingsSubmitted(
+                device_id.clone(),
+                shipment_id.clone(),
+                3
+            ))));
+
+        // Submissions cannot be replayed
+        assert_eq!(
+            validate_submission(&payload, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Stale))
+        );
+
+        // Future submissions wait for the previous ones
+        let (payload, signature) = signed_payload(2, vec![temperature(4.5, 20)]);
+        let valid_tx = validate_submission(&payload, &signature).unwrap();
+        assert!(!valid_tx.requires.is_empty());
+        assert_eq!(
+            ProductTracking::pre_dispatch(&Call::submit_readings(payload, signature)),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Future))
+        );
+    })
+}
+
+#[test]
+fn submit_readings_rate_limit() {
+    new_test_ext().execute_with(|| {
+        register_monitored_shipment(None);
+
+        for nonce in 0..2 {
+            let (payload, signature) = signed_payload(nonce, vec![temperature(4.5, 10)]);
+            assert_ok!(validate_submission(&payload, &signature));
+            assert_ok!(ProductTracking::submit_readings(Origin::none(), payload, signature));
+        }
+
+        // Max submissions of the device reached for the block
+        let (payload, signature) = signed_payload(2, vec![temperature(4.5, 10)]);
+        assert_eq!(
+            validate_submission(&payload, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources))
+        );
+
+        System::set_block_number(2);
+        assert_ok!(validate_submission(&payload, &signature));
+    })
+}
+
+#[test]
+fn submit_readings_over_limit() {
+    new_test_ext().execute_with(|| {
+        register_monitored_shipment(None);
+
+        // Max readings per submission exceeded
+        let readings = (1..=4).map(|i| temperature(4.5, i * 10)).collect::<Vec<_>>();
+        let (payload, signature) = signed_payload(0, readings);
+        assert_eq!(
+            validate_submission(&payload, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources))
+        );
+        assert_eq!(
+            ProductTracking::pre_dispatch(&Call::submit_readings(payload, signature)),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::ExhaustsResources))
+        );
+
+        let readings = (1..=3).map(|i| temperature(4.5, i * 10)).collect::<Vec<_>>();
+        let (payload, signature) = signed_payload(0, readings);
+        assert_ok!(validate_submission(&payload, &signature));
+    })
+}
+
+#[test]
+fn submit_readings_with_invalid_payload() {
+    new_test_ext().execute_with(|| {
+        register_monitored_shipment(None);
+
+        // Only unsigned transactions
+        let (payload, signature) = signed_payload(0, vec![temperature(4.5, 10)]);
+        assert_noop!(
+            ProductTracking::submit_readings(
+                Origin::signed(account_key(TEST_SENDER)),
+                payload.clone(),
+                signature.clone()
+            ),
+            dispatch::DispatchError::BadOrigin
+        );
+
+        // Tampered payload
+        let mut tampered = payload.clone();
+        tampered.readings[0].value = I16F16::from_num(3.5);
+        assert_eq!(
+            validate_submission(&tampered, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::BadProof))
+        );
+
+        // No readings
+        let (payload, signature) = signed_payload(0, vec![]);
+        assert_eq!(
+            validate_submission(&payload, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Call))
+        );
+
+        // Readings of a device of another organization
+        let mut reading = temperature(4.5, 10);
+        reading.device_id = b"Contoso-01".to_vec();
+        assert_ok!(ProductTracking::register_device(
+            Origin::signed(account_key(TEST_OTHER_SENDER)),
+            reading.device_id.clone(),
+            account_key(TEST_OTHER_ORGANIZATION),
+            device_pair().public().into(),
+            b"DataLogger".to_vec(),
+            vec![ReadingType::Temperature]
+        ));
+        let (payload, signature) = signed_payload(0, vec![reading]);
+        assert_eq!(
+            validate_submission(&payload, &signature),
+            Err(TransactionValidityError::Invalid(InvalidTransaction::Call))
+        );
+        assert_noop!(
+            ProductTracking::submit_readings(Origin::none(), payload, signature),
+            Error::<Test>::ReadingOfForeignDevice
+        );
+    })
+}
+
+#[test]
+fn listener_endpoints() {
+    let mut ext = new_test_ext();
+    let (offchain, _state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        // Defaults to the local listener
+        assert_eq!(
+            ProductTracking::listener_endpoints(),
+            vec![LISTENER_ENDPOINT.as_bytes().to_vec()]
+        );
+
+        // Only enabled endpoints are notified
+        StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY).set(&vec![
+            ListenerEndpoint {
+                url: b"http://listener-1:3005".to_vec(),
+                enabled: true,
+            },
+            ListenerEndpoint {
+                url: b"http://listener-2:3005".to_vec(),
+                enabled: false,
+            },
+        ]);
+        assert_eq!(
+            ProductTracking::listener_endpoints(),
+            vec![b"http://listener-1:3005".to_vec()]
+        );
+
+        // An empty list disables all endpoints, w/o falling back to the local listener
+        StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY).set(&Vec::<ListenerEndpoint>::new());
+        assert_eq!(ProductTracking::listener_endpoints(), Vec::<Vec<u8>>::new());
+    })
+}
+
+#[test]
+fn parse_listener_endpoint() {
+    assert_eq!(
+        ListenerEndpoint::parse("http://listener-1:3005"),
+        ListenerEndpoint {
+            url: b"http://listener-1:3005".to_vec(),
+            enabled: true,
+        }
+    );
+    assert_eq!(
+        ListenerEndpoint::parse("http://listener-1:3005,disabled"),
+        ListenerEndpoint {
+            url: b"http://listener-1:3005".to_vec(),
+            enabled: false,
+        }
+    );
+    assert_eq!(
+        ListenerEndpoint::parse("http://listener-1:3005/?a=1,b=2,enabled"),
+        ListenerEndpoint {
+            url: b"http://listener-1:3005/?a=1,b=2".to_vec(),
+            enabled: true,
+        }
+    );
+}
+
+#[test]
+fn skip_disabled_listener_endpoints() {
+    let mut ext = new_test_ext();
+    let (offchain, state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+        StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY).set(&vec![
+            ListenerEndpoint::parse(LISTENER_ENDPOINT),
+            ListenerEndpoint::parse("http://listener-2:3005,disabled"),
+        ]);
+
+        // Only the enabled endpoint is notified, any request to the disabled one being unexpected
+        expect_notification(&mut state.write(), 1, None);
+        ProductTracking::process_ocw_notifications(1);
+
+        assert_eq!(retries(), vec![]);
+        assert_eq!(dead_letters(), vec![]);
+    })
+}
+
+#[test]
+fn shipping_event_notification() {
+    let event = ShippingEvent {
+        event_type: ShippingEventType::ShipmentScan,
+        shipment_id: TEST_SHIPMENT_ID.as_bytes().to_owned(),
+        location: Some(ReadPoint {
+            latitude: I16F16::from_num(52.25),
+            longitude: I16F16::from_num(-13.5),
+        }),
+        readings: vec![
+            Reading {
+                device_id: b"Sensor \"A\"".to_vec(),
+                reading_type: ReadingType::Temperature,
+                timestamp: 42,
+                value: I16F16::from_num(20.5),
+            },
+            Reading {
+                device_id: vec![0xde, 0xad, 0xbe, 0xef],
+                reading_type: ReadingType::Humidity,
+                timestamp: 43,
+                value: I16F16::from_num(65),
+            },
+            // Printable, though looking like a hex encoded identifier
+            Reading {
+                device_id: b"0xdeadbeef".to_vec(),
+                reading_type: ReadingType::Humidity,
+                timestamp: 44,
+                value: I16F16::from_num(64),
+            },
+        ],
+        timestamp: 42u64,
+    };
+    let notification = Notification {
+        event_idx: 3,
+        block_number: 7u64,
+        block_hash: Some(sp_core::H256::repeat_byte(0xcd)),
+        parent_hash: Some(sp_core::H256::repeat_byte(0xab)),
+        event: &event,
+    };
+
+    assert_eq!(
+        notification.to_json(),
+        format!(
+            concat!(
+                r#"{{"version":2,"event_index":3,"block_number":7,"block_hash":"0x{}","parent_hash":"0x{}","#,
+                r#""event":{{"type":"ShipmentScan","shipment_id":{{"utf8":"0001"}},"timestamp":42,"#,
+                r#""location":{{"latitude":52.25,"longitude":-13.5}},"readings":["#,
+                r#"{{"device_id":{{"utf8":"Sensor \"A\""}},"reading_type":"Temperature","timestamp":42,"value":20.5}},"#,
+                r#"{{"device_id":{{"hex":"0xdeadbeef"}},"reading_type":"Humidity","timestamp":43,"value":65}},"#,
+                r#"{{"device_id":{{"utf8":"0xdeadbeef"}},"reading_type":"Humidity","timestamp":44,"value":64}}]}}}}"#
+            ),
+            "cd".repeat(32),
+            "ab".repeat(32)
+        )
+    );
+
+    // The hash of the block is unknown to its own off-chain worker
+    let notification = Notification {
+        block_hash: None,
+        ..notification
+    };
+    assert!(notification.to_json().starts_with(&format!(
+        r#"{{"version":2,"event_index":3,"block_number":7,"block_hash":null,"parent_hash":"0x{}","#,
+        "ab".repeat(32)
+    )));
+
+    // The hash of the parent block is unknown for the genesis block, or once pruned
+    let notification = Notification {
+        parent_hash: None,
+        ..notification
+    };
+    assert!(notification
+        .to_json()
+        .starts_with(r#"{"version":2,"event_index":3,"block_number":7,"block_hash":null,"parent_hash":null,"#));
+}
+
+// Hash of the parent of block 1, in which the test events are recorded
+fn test_parent_hash() -> Option<Vec<u8>> {
+    Some(System::block_hash(0).as_ref().to_vec())
+}
+
+// Expects the notification of an event of block 1, whose hash is only known to the off-chain
+// workers of the next blocks
+fn expect_notification(
+    state: &mut testing::OffchainState,
+    idx: ShippingEventIndex,
+    block_hash: Option<sp_core::H256>,
+) {
+    let event = ProductTracking::event_by_idx(idx).unwrap();
+    // The block is identified by a known hash, even when notified by its own off-chain worker
+    assert_ne!(System::block_hash(0), sp_core::H256::zero());
+    let payload = Notification {
+        event_idx: idx,
+        block_number: 1u64,
+        block_hash: block_hash.map(|hash| hash.as_ref().to_vec()),
+        parent_hash: test_parent_hash(),
+        event: &event,
+    }
+    .to_json();
+    state.expect_request(testing::PendingRequest {
+        method: "POST".into(),
+        uri: LISTENER_ENDPOINT.into(),
+        body: payload.into_bytes(),
+        headers: vec![("Content-Type".into(), "application/json".into())],
+        response: Some(br#"{"status":"acknowledged"}"#.to_vec()),
+        sent: true,
+        ..Default::default()
+    });
+}
+
+fn delivery_of(idx: ShippingEventIndex) -> Option<Delivery> {
+    StorageValueRef::persistent(&delivery_key(idx)).get::<Delivery>().flatten()
+}
+
+fn retries() -> Vec<ShippingEventIndex> {
+    StorageValueRef::persistent(RETRIES_KEY)
+        .get::<Vec<ShippingEventIndex>>()
+        .flatten()
+        .unwrap_or_default()
+}
+
+fn dead_letters() -> Vec<(ShippingEventIndex, Delivery)> {
+    StorageValueRef::persistent(DEAD_LETTERS_KEY)
+        .get::<Vec<(ShippingEventIndex, Delivery)>>()
+        .flatten()
+        .unwrap_or_default()
+}
+
+#[test]
+fn deliver_notifications() {
+    let mut ext = new_test_ext();
+    let (offchain, state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+        // Block 1 has been imported
+        let block_hash = sp_core::H256::repeat_byte(0xcd);
+        frame_system::BlockHash::<Test>::insert(1, block_hash);
+        expect_notification(&mut state.write(), 1, Some(block_hash));
+
+        ProductTracking::process_ocw_notifications(2);
+
+        // The delivery state is cleared once delivered
+        assert_eq!(delivery_of(1), None);
+        assert_eq!(
+            StorageValueRef::persistent(RETRIES_KEY).get::<Vec<ShippingEventIndex>>(),
+            Some(Some(vec![]))
+        );
+        assert_eq!(dead_letters(), vec![]);
+    })
+}
+
+#[test]
+fn dead_letter_notifications() {
+    let mut ext = new_test_ext();
+    let (offchain, _state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+
+        // Last attempt of a pending delivery
+        StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block").set(&1u64);
+        StorageValueRef::persistent(&delivery_key(1)).set(&Delivery {
+            block_number: 1,
+            block_hash: None,
+            parent_hash: test_parent_hash(),
+            status: DeliveryStatus::Pending,
+            attempts: DELIVERY_MAX_ATTEMPTS - 1,
+            next_attempt: 2,
+            notified: vec![],
+            last_error: b"http response error".to_vec(),
+        });
+        StorageValueRef::persistent(RETRIES_KEY).set(&vec![1u128]);
+        // Unreachable listener endpoint
+        StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY).set(&vec![ListenerEndpoint {
+            url: vec![0xff],
+            enabled: true,
+        }]);
+
+        ProductTracking::process_ocw_notifications(2);
+
+        // The delivery state moves along with the dead letter
+        assert_eq!(delivery_of(1), None);
+        assert_eq!(retries(), vec![]);
+        assert_eq!(
+            dead_letters(),
+            vec![(
+                1,
+                Delivery {
+                    block_number: 1,
+                    block_hash: None,
+                    parent_hash: test_parent_hash(),
+                    status: DeliveryStatus::DeadLetter,
+                    attempts: DELIVERY_MAX_ATTEMPTS,
+                    next_attempt: 2 + 128,
+                    notified: vec![],
+                    last_error: b"invalid listener endpoint".to_vec(),
+                }
+            )]
+        );
+    })
+}
+
+#[test]
+fn replay_dead_letters() {
+    let mut ext = new_test_ext();
+    let (offchain, state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+
+        // Notification given up in a previous run
+        StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block").set(&1u64);
+        let dead_letter = Delivery {
+            block_number: 1,
+            block_hash: None,
+            parent_hash: test_parent_hash(),
+            status: DeliveryStatus::DeadLetter,
+            attempts: DELIVERY_MAX_ATTEMPTS,
+            next_attempt: 0,
+            notified: vec![],
+            last_error: b"http response error".to_vec(),
+        };
+        StorageValueRef::persistent(DEAD_LETTERS_KEY).set(&vec![(1u128, dead_letter.clone())]);
+
+        // Dead letters stay dead until replayed
+        ProductTracking::process_ocw_notifications(2);
+        assert_eq!(dead_letters(), vec![(1, dead_letter)]);
+        assert_eq!(delivery_of(1), None);
+
+        StorageValueRef::persistent(REPLAYS_KEY).set(&vec![1u128]);
+        expect_notification(&mut state.write(), 1, None);
+
+        ProductTracking::process_ocw_notifications(3);
+
+        assert_eq!(delivery_of(1), None);
+        assert_eq!(retries(), vec![]);
+        assert_eq!(dead_letters(), vec![]);
+        assert_eq!(
+            StorageValueRef::persistent(REPLAYS_KEY).get::<Vec<ShippingEventIndex>>(),
+            Some(Some(vec![]))
+        );
+    })
+}
+
+#[test]
+fn deliver_notifications_attempted_by_aborted_run() {
+    let mut ext = new_test_ext();
+    let (offchain, _state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+
+        // Listener notified by a run aborted before saving block 1 as processed
+        StorageValueRef::persistent(&delivery_key(1)).set(&Delivery {
+            block_number: 1,
+            block_hash: None,
+            parent_hash: test_parent_hash(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: 1,
+            notified: vec![LISTENER_ENDPOINT.as_bytes().to_vec()],
+            last_error: vec![],
+        });
+
+        // The listener is not notified again, any request being unexpected
+        ProductTracking::process_ocw_notifications(1);
+
+        assert_eq!(delivery_of(1), None);
+        assert_eq!(retries(), vec![]);
+        assert_eq!(dead_letters(), vec![]);
+    })
+}
+
+#[test]
+fn dead_letters_are_capped() {
+    let mut ext = new_test_ext();
+    let (offchain, _state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+
+        let dead_letter = Delivery {
+            block_number: 1,
+            block_hash: None,
+            parent_hash: test_parent_hash(),
+            status: DeliveryStatus::DeadLetter,
+            attempts: DELIVERY_MAX_ATTEMPTS,
+            next_attempt: 0,
+            notified: vec![],
+            last_error: b"http response error".to_vec(),
+        };
+        let full: Vec<_> = (0..DEAD_LETTERS_MAX as ShippingEventIndex)
+            .map(|idx| (100 + idx, dead_letter.clone()))
+            .collect();
+        StorageValueRef::persistent(DEAD_LETTERS_KEY).set(&full);
+
+        // Last attempt of a pending delivery
+        StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block").set(&1u64);
+        StorageValueRef::persistent(&delivery_key(1)).set(&Delivery {
+            status: DeliveryStatus::Pending,
+            attempts: DELIVERY_MAX_ATTEMPTS - 1,
+            next_attempt: 2,
+            ..dead_letter.clone()
+        });
+        StorageValueRef::persistent(RETRIES_KEY).set(&vec![1u128]);
+        StorageValueRef::persistent(LISTENER_ENDPOINTS_KEY).set(&vec![ListenerEndpoint {
+            url: vec![0xff],
+            enabled: true,
+        }]);
+
+        ProductTracking::process_ocw_notifications(2);
+
+        // The oldest dead letter is dropped
+        let dead_letters = dead_letters();
+        assert_eq!(dead_letters.len(), DEAD_LETTERS_MAX);
+        assert_eq!(dead_letters[0].0, 101);
+        assert_eq!(dead_letters[DEAD_LETTERS_MAX - 1].0, 1);
+    })
+}
+
+#[test]
+fn prune_ocw_notifications() {
+    new_test_ext().execute_with(|| {
+        for block_number in 1..=4u64 {
+            <OcwNotifications<Test>>::append(block_number, block_number as ShippingEventIndex);
+        }
+
+        // Nothing to prune within the retention window
+        ProductTracking::on_initialize(5);
+        assert_eq!(ProductTracking::ocw_notifications_pruned(), 0);
+        assert_eq!(ProductTracking::ocw_notifications(1), vec![1]);
+
+        // At most 2 blocks pruned per block
+        ProductTracking::on_initialize(9);
+        assert_eq!(ProductTracking::ocw_notifications_pruned(), 2);
+        assert_eq!(ProductTracking::ocw_notifications(1), Vec::<ShippingEventIndex>::new());
+        assert_eq!(ProductTracking::ocw_notifications(2), vec![2]);
+
+        ProductTracking::on_initialize(10);
+        assert_eq!(ProductTracking::ocw_notifications_pruned(), 4);
+        assert_eq!(ProductTracking::ocw_notifications(3), Vec::<ShippingEventIndex>::new());
+        assert_eq!(ProductTracking::ocw_notifications(4), vec![4]);
+
+        // Blocks within the retention window are kept
+        ProductTracking::on_initialize(9);
+        assert_eq!(ProductTracking::ocw_notifications_pruned(), 4);
+        assert_eq!(ProductTracking::ocw_notifications(4), vec![4]);
+    })
+}
+
+#[test]
+fn ocw_catch_up_window() {
+    let mut ext = new_test_ext();
+    let (offchain, state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        let last_processed_block = || {
+            StorageValueRef::persistent(b"product_tracking_ocw::last_proccessed_block")
+                .get::<u64>()
+                .flatten()
+        };
+        register_test_shipment();
+
+        // Fresh node, only the last `OcwMaxBacktrackPeriod` blocks are processed
+        ProductTracking::process_ocw_notifications(5);
+        assert_eq!(retries(), vec![]);
+        assert_eq!(last_processed_block(), Some(5));
+
+        // Explicit start block, processed once
+        StorageValueRef::persistent(START_BLOCK_KEY).set(&1u64);
+        expect_notification(&mut state.write(), 1, None);
+
+        ProductTracking::process_ocw_notifications(6);
+
+        assert_eq!(retries(), vec![]);
+        assert_eq!(last_processed_block(), Some(6));
+        assert_eq!(StorageValueRef::persistent(START_BLOCK_KEY).get::<u64>(), None);
+    });
+
+    let mut ext = new_test_ext();
+    let (offchain, state) = testing::TestOffchainExt::new();
+    ext.register_extension(OffchainExt::new(offchain));
+
+    ext.execute_with(|| {
+        register_test_shipment();
+        expect_notification(&mut state.write(), 1, None);
+
+        // Notifications of the current block are not deferred
+        ProductTracking::process_ocw_notifications(1);
+
+        assert_eq!(retries(), vec![]);
+        assert_eq!(delivery_of(1), None);
+    })
+}
@@ -1,8 +1,11 @@
 use codec::{Decode, Encode};
 use core::fmt;
-use fixed::types::I16F16;
-use frame_support::{sp_runtime::RuntimeDebug, sp_std::prelude::*};
-use product_registry::ProductId;
+use fixed::types::{I16F16, I32F32};
+use frame_support::{
+    sp_runtime::{MultiSignature, MultiSigner, RuntimeDebug},
+    sp_std::prelude::*,
+};
+use product_registry::{LotId, ProductId};
 
 // Custom types
 pub type Identifier = Vec<u8>;
@@ -10,12 +13,18 @@ pub type Decimal = I16F16;
 pub type ShipmentId = Identifier;
 pub type ShippingEventIndex = u128;
 pub type DeviceId = Identifier;
+pub type DeviceType = Identifier;
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub enum ShipmentStatus {
     Pending,
     InTransit,
     Delivered,
+    Cancelled,
+    Returned,
+    Lost,
+    Damaged,
+    Rejected,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -23,20 +32,81 @@ pub struct Shipment<AccountId, Moment> {
     pub id: ShipmentId,
     pub owner: AccountId,
     pub status: ShipmentStatus,
-    pub products: Vec<ProductId>,
+    pub products: Vec<ShippedProduct>,
     pub registered: Moment,
     pub delivered: Option<Moment>,
+    pub itinerary: Vec<Leg<AccountId, Moment>>,
 }
 
-impl<AccountId, Moment> Shipment<AccountId, Moment> {
-    pub fn pickup(mut self) -> Self {
-        self.status = ShipmentStatus::InTransit;
-        self
+// A leg of a shipment's planned itinerary, assigned to a carrier.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Leg<AccountId, Moment> {
+    pub origin: ReadPoint,
+    pub destination: ReadPoint,
+    pub departure: Moment,
+    pub arrival: Moment,
+    pub carrier: AccountId,
+    pub completed: Option<Moment>,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Deviation {
+    // The shipment was scanned too far from the leg's route
+    OffRoute,
+    // The shipment was scanned outside of the leg's planned time window
+    OutsideTimeWindow,
+}
+
+// A product in a shipment, optionally from a given lot.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ShippedProduct {
+    pub product_id: ProductId,
+    pub lot_id: Option<LotId>,
+}
+
+// Storage version of the pallet, used to run storage migrations on runtime upgrades.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Releases {
+    V1_0_0,
+    V2_0_0,
+    V3_0_0,
+    V4_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
+impl ShipmentStatus {
+    // Whether the shipment reached the end of its lifecycle
+    pub fn is_final(&self) -> bool {
+        match self {
+            ShipmentStatus::Delivered
+            | ShipmentStatus::Cancelled
+            | ShipmentStatus::Returned
+            | ShipmentStatus::Lost => true,
+            _ => false,
+        }
     }
+}
+
+// A pending handover of a shipment's custody, to be accepted by the receiving organization
+// before it expires.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct CustodyHandover<AccountId, BlockNumber> {
+    pub from: AccountId,
+    pub to: AccountId,
+    pub expires: BlockNumber,
+}
 
-    pub fn deliver(mut self, delivered_on: Moment) -> Self {
-        self.status = ShipmentStatus::Delivered;
-        self.delivered = Some(delivered_on);
+impl<AccountId, Moment> Shipment<AccountId, Moment> {
+    pub fn with_status(mut self, status: ShipmentStatus, timestamp: Moment) -> Self {
+        if status == ShipmentStatus::Delivered {
+            self.delivered = Some(timestamp);
+        }
+        self.status = status;
         self
     }
 }
@@ -46,6 +116,11 @@ pub enum ShippingOperation {
     Pickup,
     Scan,
     Deliver,
+    Cancel,
+    Return,
+    ReportLoss,
+    ReportDamage,
+    Reject,
 }
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
@@ -54,6 +129,35 @@ pub enum ShippingEventType {
     ShipmentPickup,
     ShipmentScan,
     ShipmentDeliver,
+    ShipmentRecall,
+    ShipmentCancel,
+    ShipmentReturn,
+    ShipmentLoss,
+    ShipmentDamage,
+    ShipmentReject,
+    ShipmentHandoverPropose,
+    ShipmentHandoverAccept,
+    ShipmentHandoverDecline,
+}
+
+impl ShippingEventType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShippingEventType::ShipmentRegistration => "ShipmentRegistration",
+            ShippingEventType::ShipmentPickup => "ShipmentPickup",
+            ShippingEventType::ShipmentScan => "ShipmentScan",
+            ShippingEventType::ShipmentDeliver => "ShipmentDeliver",
+            ShippingEventType::ShipmentRecall => "ShipmentRecall",
+            ShippingEventType::ShipmentCancel => "ShipmentCancel",
+            ShippingEventType::ShipmentReturn => "ShipmentReturn",
+            ShippingEventType::ShipmentLoss => "ShipmentLoss",
+            ShippingEventType::ShipmentDamage => "ShipmentDamage",
+            ShippingEventType::ShipmentReject => "ShipmentReject",
+            ShippingEventType::ShipmentHandoverPropose => "ShipmentHandoverPropose",
+            ShippingEventType::ShipmentHandoverAccept => "ShipmentHandoverAccept",
+            ShippingEventType::ShipmentHandoverDecline => "ShipmentHandoverDecline",
+        }
+    }
 }
 
 impl From<ShippingOperation> for ShippingEventType {
@@ -62,6 +166,11 @@ impl From<ShippingOperation> for ShippingEventType {
             ShippingOperation::Pickup => ShippingEventType::ShipmentPickup,
             ShippingOperation::Scan => ShippingEventType::ShipmentScan,
             ShippingOperation::Deliver => ShippingEventType::ShipmentDeliver,
+            ShippingOperation::Cancel => ShippingEventType::ShipmentCancel,
+            ShippingOperation::Return => ShippingEventType::ShipmentReturn,
+            ShippingOperation::ReportLoss => ShippingEventType::ShipmentLoss,
+            ShippingOperation::ReportDamage => ShippingEventType::ShipmentDamage,
+            ShippingOperation::Reject => ShippingEventType::ShipmentReject,
         }
     }
 }
@@ -90,6 +199,48 @@ pub struct ReadPoint {
     pub longitude: Decimal,
 }
 
+impl ReadPoint {
+    fn coordinates(&self) -> (I32F32, I32F32) {
+        (I32F32::from_num(self.longitude), I32F32::from_num(self.latitude))
+    }
+
+    // Squared distance in degrees (of latitude) to another point.
+    pub fn distance_sq(&self, other: &ReadPoint) -> I32F32 {
+        self.distance_sq_to_segment(other, other)
+    }
+
+    // Squared distance in degrees (of latitude) to the closest point of the segment between `from` & `to`,
+    // on an equirectangular projection: longitudes are scaled by the cosine of the points' mean latitude.
+    pub fn distance_sq_to_segment(&self, from: &ReadPoint, to: &ReadPoint) -> I32F32 {
+        let (px, py) = self.coordinates();
+        let (ax, ay) = from.coordinates();
+        let (bx, by) = to.coordinates();
+        let scale = cos_latitude((py + ay + by) / I32F32::from_num(3));
+        let (px, ax, bx) = (px * scale, ax * scale, bx * scale);
+        let (dx, dy) = (bx - ax, by - ay);
+
+        // Position of the closest point along the segment, between 0 (from) & 1 (to)
+        let len_sq = dx * dx + dy * dy;
+        let t = if len_sq == I32F32::from_num(0) {
+            I32F32::from_num(0)
+        } else {
+            (((px - ax) * dx + (py - ay) * dy) / len_sq)
+                .max(I32F32::from_num(0))
+                .min(I32F32::from_num(1))
+        };
+
+        let (cx, cy) = (ax + t * dx, ay + t * dy);
+        (px - cx) * (px - cx) + (py - cy) * (py - cy)
+    }
+}
+
+// Cosine of a latitude in degrees, after Bhaskara I's approximation (absolute error below 0.002).
+fn cos_latitude(latitude: I32F32) -> I32F32 {
+    let latitude = latitude.max(I32F32::from_num(-90)).min(I32F32::from_num(90));
+    let latitude_sq = latitude * latitude;
+    (I32F32::from_num(32_400) - latitude_sq * I32F32::from_num(4)) / (I32F32::from_num(32_400) + latitude_sq)
+}
+
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub enum ReadingType {
     Humidity,
@@ -100,6 +251,48 @@ pub enum ReadingType {
     Vibration,
 }
 
+impl ReadingType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReadingType::Humidity => "Humidity",
+            ReadingType::Pressure => "Pressure",
+            ReadingType::Shock => "Shock",
+            ReadingType::Tilt => "Tilt",
+            ReadingType::Temperature => "Temperature",
+            ReadingType::Vibration => "Vibration",
+        }
+    }
+}
+
+// Monitoring threshold of a shipment's condition, for a type of reading.
+// Readings below `min` or above `max` are out of range, and the condition is breached
+// once the readings have been out of range for more than `max_excursion` in total,
+// or as soon as a reading is out of range if there is no `max_excursion`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ConditionThreshold<Moment> {
+    pub reading_type: ReadingType,
+    pub min: Option<Decimal>,
+    pub max: Option<Decimal>,
+    pub max_excursion: Option<Moment>,
+}
+
+impl<Moment> ConditionThreshold<Moment> {
+    pub fn in_range(&self, value: &Decimal) -> bool {
+        self.min.map_or(true, |min| *value >= min) && self.max.map_or(true, |max| *value <= max)
+    }
+}
+
+// Time spent out of range by a shipment, for a type of reading. An excursion starts with
+// an out of range reading, and ends with the next reading in range.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct Excursion<Moment> {
+    pub last_reading: Moment,
+    pub out_of_range: bool,
+    pub duration: Moment,
+    // Whether a breach was reported during the current (or last) excursion
+    pub breached: bool,
+}
+
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
 pub struct Reading<Moment> {
     pub device_id: DeviceId,
@@ -108,3 +301,90 @@ pub struct Reading<Moment> {
     pub timestamp: Moment,
     pub value: Decimal,
 }
+
+// A reading, signed by the device it originates from.
+// The signature is over the SCALE-encoded (shipment ID, reading type, timestamp, value) tuple.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct SignedReading<Moment> {
+    pub reading: Reading<Moment>,
+    pub signature: MultiSignature,
+}
+
+// A batch of readings for a shipment, submitted through an unsigned transaction
+// by a registered device (e.g. an IoT gateway), and signed by the device's key.
+// The `nonce` must be the device's next nonce, to prevent replays.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ReadingsPayload<Moment> {
+    pub device_id: DeviceId,
+    pub nonce: u64,
+    pub shipment_id: ShipmentId,
+    pub readings: Vec<Reading<Moment>>,
+}
+
+// An IoT device registered by an organization, whose readings are signed with its key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Device<AccountId> {
+    pub id: DeviceId,
+    pub owner: AccountId,
+    pub key: MultiSigner,
+    pub device_type: DeviceType,
+    pub reading_types: Vec<ReadingType>,
+    pub active: bool,
+}
+
+// An HTTP endpoint notified of the shipping events by the off-chain worker,
+// as configured in the offchain local storage of the node.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ListenerEndpoint {
+    pub url: Vec<u8>,
+    pub enabled: bool,
+}
+
+impl ListenerEndpoint {
+    // Parses an endpoint given as `URL`, or `URL,disabled` to keep it configured
+    // but not notified (e.g. while the listener is under maintenance).
+    pub fn parse(value: &str) -> Self {
+        let (url, enabled) = match value.rfind(',') {
+            Some(pos) if &value[pos + 1..] == "disabled" => (&value[..pos], false),
+            Some(pos) if &value[pos + 1..] == "enabled" => (&value[..pos], true),
+            _ => (value, true),
+        };
+        ListenerEndpoint {
+            url: url.as_bytes().to_vec(),
+            enabled,
+        }
+    }
+}
+
+// Delivery state of a shipping event notification, in the offchain local storage.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Delivery {
+    // Block in which the shipping event was recorded, its hash (once known) & the hash of its parent block
+    // (unless unknown)
+    pub block_number: u32,
+    pub block_hash: Option<Vec<u8>>,
+    pub parent_hash: Option<Vec<u8>>,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    // Block from which the delivery can be attempted again
+    pub next_attempt: u32,
+    // Listener endpoints already notified
+    pub notified: Vec<Vec<u8>>,
+    pub last_error: Vec<u8>,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    // Given up after too many attempts, until replayed
+    DeadLetter,
+}
+
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum NotifyError {
+    // The request could not be sent, or timed out
+    Unreachable(&'static str),
+    // The listener answered with an error status code
+    Rejected(u16),
+}
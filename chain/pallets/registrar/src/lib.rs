@@ -8,6 +8,12 @@ use frame_support::{
 };
 use frame_system::{self as system, ensure_signed, RawOrigin};
 
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
 /// Configure the pallet by specifying the parameters and types on which it depends.
 pub trait Trait: system::Trait + did::Trait {
 	/// Because this pallet emits events, it depends on the runtime's definition of an event.
@@ -34,6 +40,10 @@ decl_event!(
 		CreatedOrganization(AccountId, Vec<u8>),
 		/// An account was added to an organization. [account, organization_id]
 		AddedToOrganization(AccountId, Vec<u8>),
+		/// An account was authorized to act on behalf of an organization. [organization, delegate]
+		AuthorizedDelegate(AccountId, AccountId),
+		/// The authorization of an account to act on behalf of an organization was revoked. [organization, delegate]
+		RevokedDelegate(AccountId, AccountId),
 	}
 );
 
@@ -101,6 +111,39 @@ decl_module! {
 			Self::deposit_event(RawEvent::AddedToOrganization(who, b"OrgMember".to_vec()));
 			Ok(())
 		}
+
+		/// Authorize an account, which is not a member, to act on behalf of an organization e.g. a
+		/// logistics provider registering shipments. The authorization can be limited to a number of blocks.
+		/// Will return an InvalidOrganization error if the organization does not exist.
+		/// Will emit a AuthorizedDelegate event on success.
+		///
+		/// The dispatch origin for this call must be Signed.
+		#[weight = 10_000]
+		pub fn authorize_delegate(origin, delegate: T::AccountId, valid_for: Option<T::BlockNumber>) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::organizations().contains(&who), Error::<T>::InvalidOrganization);
+
+			// Add account as a DID delegate.
+			<did::Module<T>>::create_delegate(&who, &who, &delegate, &b"OrgDelegate".to_vec(), valid_for)?;
+			Self::deposit_event(RawEvent::AuthorizedDelegate(who, delegate));
+			Ok(())
+		}
+
+		/// Revoke the authorization of an account to act on behalf of an organization, with immediate effect.
+		/// Will return an InvalidOrganization error if the organization does not exist.
+		/// Will emit a RevokedDelegate event on success.
+		///
+		/// The dispatch origin for this call must be Signed.
+		#[weight = 10_000]
+		pub fn revoke_delegate(origin, delegate: T::AccountId) -> dispatch::DispatchResult {
+			let who = ensure_signed(origin.clone())?;
+			ensure!(Self::organizations().contains(&who), Error::<T>::InvalidOrganization);
+
+			// Revoke the account's DID delegate.
+			<did::Module<T>>::revoke_delegate(origin, who.clone(), b"OrgDelegate".to_vec(), delegate.clone())?;
+			Self::deposit_event(RawEvent::RevokedDelegate(who, delegate));
+			Ok(())
+		}
 	}
 }
 
@@ -138,7 +181,15 @@ impl<T: Trait> Module<T> {
 		Ok(())
 	}
 
-	/// Returns true if and only if the account is a member of an organization.
+	/// Returns true if and only if the account is a member, or a (still valid) authorized delegate,
+	/// of the given organization.
+	pub fn is_member_or_delegate(org: &T::AccountId, account: &T::AccountId) -> bool {
+		<did::Module<T>>::valid_delegate(org, &b"OrgMember".to_vec(), account).is_ok()
+			|| <did::Module<T>>::valid_delegate(org, &b"OrgDelegate".to_vec(), account).is_ok()
+	}
+
+	/// Returns true if and only if the account is a member of an organization. Authorized delegates are not,
+	/// they only act on behalf of their organization where the pallets check it (see `is_member_or_delegate`).
 	pub fn part_of_organization(account: &T::AccountId) -> bool {
 		let orgs = <Module<T>>::organizations();
 		for org in orgs.iter() {
@@ -0,0 +1,82 @@
+use super::*;
+use crate::mock::*;
+use frame_support::{assert_noop, assert_ok};
+
+const TEST_ORGANIZATION: &str = "Northwind";
+const TEST_MEMBER: &str = "Alice";
+const TEST_DELEGATE: &str = "Bob";
+
+fn create_test_organization() -> sp_core::sr25519::Public {
+	let org = account_key(TEST_ORGANIZATION);
+	assert_ok!(Registrar::create_organization(Origin::signed(org), b"Northwind".to_vec()));
+	org
+}
+
+fn ensure_org(account: &str) -> bool {
+	EnsureOrg::<Test>::try_origin(Origin::signed(account_key(account))).is_ok()
+}
+
+#[test]
+fn members_pass_ensure_org() {
+	new_test_ext().execute_with(|| {
+		let org = create_test_organization();
+		assert!(!ensure_org(TEST_MEMBER));
+
+		assert_ok!(Registrar::add_to_organization(Origin::signed(org), account_key(TEST_MEMBER)));
+
+		assert!(Registrar::is_member_or_delegate(&org, &account_key(TEST_MEMBER)));
+		assert!(ensure_org(TEST_MEMBER));
+	})
+}
+
+#[test]
+fn authorize_and_revoke_delegate() {
+	new_test_ext().execute_with(|| {
+		let org = create_test_organization();
+		let delegate = account_key(TEST_DELEGATE);
+
+		assert_ok!(Registrar::authorize_delegate(Origin::signed(org), delegate, None));
+
+		// Authorized delegates act on behalf of the organization, but are not consortium members
+		assert!(Registrar::is_member_or_delegate(&org, &delegate));
+		assert!(!ensure_org(TEST_DELEGATE));
+		assert!(System::events().iter().any(|er| er.event
+			== TestEvent::registrar(RawEvent::AuthorizedDelegate(org, delegate))));
+
+		assert_ok!(Registrar::revoke_delegate(Origin::signed(org), delegate));
+
+		assert!(!Registrar::is_member_or_delegate(&org, &delegate));
+		assert!(System::events().iter().any(|er| er.event
+			== TestEvent::registrar(RawEvent::RevokedDelegate(org, delegate))));
+	})
+}
+
+#[test]
+fn delegate_authorization_expires() {
+	new_test_ext().execute_with(|| {
+		let org = create_test_organization();
+		let delegate = account_key(TEST_DELEGATE);
+
+		assert_ok!(Registrar::authorize_delegate(Origin::signed(org), delegate, Some(5)));
+		assert!(Registrar::is_member_or_delegate(&org, &delegate));
+
+		System::set_block_number(10);
+		assert!(!Registrar::is_member_or_delegate(&org, &delegate));
+	})
+}
+
+#[test]
+fn revoke_delegate_of_unknown_organization() {
+	new_test_ext().execute_with(|| {
+		let org = create_test_organization();
+		let delegate = account_key(TEST_DELEGATE);
+		assert_ok!(Registrar::authorize_delegate(Origin::signed(org), delegate, None));
+
+		// Only the organization can revoke its delegates
+		assert_noop!(
+			Registrar::revoke_delegate(Origin::signed(account_key(TEST_MEMBER)), delegate),
+			Error::<Test>::InvalidOrganization
+		);
+		assert!(Registrar::revoke_delegate(Origin::signed(org), account_key(TEST_MEMBER)).is_err());
+	})
+}
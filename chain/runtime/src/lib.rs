@@ -10,7 +10,7 @@ use sp_std::prelude::*;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata, u32_trait::{_1, _2, _4, _5}};
 use sp_runtime::{
 	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature,
-	transaction_validity::{TransactionValidity, TransactionSource}, MultiSigner
+	transaction_validity::{TransactionLongevity, TransactionPriority, TransactionValidity, TransactionSource}, MultiSigner
 };
 use sp_runtime::traits::{
 	BlakeTwo256, Block as BlockT, IdentityLookup, Verify, IdentifyAccount,
@@ -66,6 +66,9 @@ pub type Index = u32;
 /// A hash of some data used by the chain.
 pub type Hash = sp_core::H256;
 
+/// A timestamp, in milliseconds since the Unix epoch.
+pub type Moment = u64;
+
 /// Digest item type.
 pub type DigestItem = generic::DigestItem<Hash>;
 
@@ -98,7 +101,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("enterprise-sample"),
     impl_name: create_runtime_str!("enterprise-sample"),
     authoring_version: 1,
-    spec_version: 1,
+    spec_version: 6,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 1,
@@ -226,7 +229,7 @@ parameter_types! {
 
 impl pallet_timestamp::Trait for Runtime {
     /// A timestamp: milliseconds since the unix epoch.
-    type Moment = u64;
+    type Moment = Moment;
     type OnTimestampSet = Aura;
     type MinimumPeriod = MinimumPeriod;
     type WeightInfo = ();
@@ -433,14 +436,109 @@ impl registrar::Trait for Runtime {
     type Event = Event;
 }
 
+/// Provides the GS1 Company Prefix of an organization from its DID attributes.
+pub struct OrgCompanyPrefix;
+
+impl product_registry::CompanyPrefixProvider<AccountId> for OrgCompanyPrefix {
+    fn company_prefix(org: &AccountId) -> Option<Vec<u8>> {
+        PalletDid::attribute_and_id(org, product_registry::gs1::COMPANY_PREFIX_ATTRIBUTE)
+            .map(|(attribute, _)| attribute)
+            .filter(|attribute| attribute.validity > System::block_number())
+            .map(|attribute| attribute.value)
+    }
+}
+
+// Consortium organizations are the ones created in the registrar pallet,
+// accounts act on their behalf as members or authorized delegates.
+pub struct ConsortiumOrganizations;
+
+impl product_registry::OrganizationProvider<AccountId> for ConsortiumOrganizations {
+    fn is_organization(org: &AccountId) -> bool {
+        Registrar::organizations().contains(org)
+    }
+
+    fn is_member_or_delegate(org: &AccountId, account: &AccountId) -> bool {
+        Registrar::is_member_or_delegate(org, account)
+    }
+}
+
+parameter_types! {
+    pub const EnforceGs1: bool = false;
+    pub const ProductIdMaxLength: u32 = 36;
+    pub const ProductMaxProps: u32 = 20;
+    pub const ProductPropNameMaxLength: u32 = 32;
+    pub const ProductPropValueMaxLength: u32 = 256;
+    pub const LotIdMaxLength: u32 = 36;
+    pub const ProductBatchMaxSize: u32 = 500;
+    pub const CertificateIdMaxLength: u32 = 64;
+    pub const CertificationSchemeMaxLength: u32 = 32;
+    pub const SchemaIdMaxLength: u32 = 36;
+    pub const BomMaxComponents: u32 = 10;
+    pub const BomMaxDepth: u32 = 3;
+}
+
 impl product_registry::Trait for Runtime {
     type Event = Event;
-    type CreateRoleOrigin = registrar::EnsureOrg<Runtime>;
+    type OnProductRecall = ProductTracking;
+    type Organizations = ConsortiumOrganizations;
+    type EnforceGs1 = EnforceGs1;
+    type CompanyPrefixes = OrgCompanyPrefix;
+    // Product certifiers are accredited by root or a council majority, not by organizations themselves
+    type AccreditationOrigin = frame_system::EnsureOneOf<AccountId,
+        pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>,
+        frame_system::EnsureRoot<AccountId>,
+    >;
+    type ProductIdMaxLength = ProductIdMaxLength;
+    type ProductMaxProps = ProductMaxProps;
+    type ProductPropNameMaxLength = ProductPropNameMaxLength;
+    type ProductPropValueMaxLength = ProductPropValueMaxLength;
+    type LotIdMaxLength = LotIdMaxLength;
+    type ProductBatchMaxSize = ProductBatchMaxSize;
+    type CertificateIdMaxLength = CertificateIdMaxLength;
+    type CertificationSchemeMaxLength = CertificationSchemeMaxLength;
+    type SchemaIdMaxLength = SchemaIdMaxLength;
+    type BomMaxComponents = BomMaxComponents;
+    type BomMaxDepth = BomMaxDepth;
+}
+
+parameter_types! {
+    pub const IdentifierMaxLength: u32 = 36;
+    pub const ShipmentMaxProducts: u32 = 10;
+    pub const HandoverTimeout: BlockNumber = 1 * DAYS;
+    pub const EnforceProductOwnership: bool = true;
+    pub const ItineraryMaxLegs: u32 = 10;
+    pub const DeviationRadius: u32 = 5_000;
+    pub const DeviationTimeTolerance: Moment = 2 * 60 * 60 * 1000;
+    pub const DeviceMaxSubmissions: u32 = 2;
+    pub const MaxReadingsPerSubmission: u32 = 32;
+    pub const TrackingUnsignedPriority: TransactionPriority = TransactionPriority::max_value() / 2;
+    pub const TrackingUnsignedLongevity: TransactionLongevity = 10;
+    pub const NotificationRetention: BlockNumber = 1 * DAYS;
+    pub const NotificationMaxPrune: u32 = 16;
+    pub const OcwMaxBacktrackPeriod: BlockNumber = 1 * HOURS;
+    pub const MaxShipmentsPerRecall: u32 = 50;
 }
 
 impl product_tracking::Trait for Runtime {
     type Event = Event;
     type CreateRoleOrigin = registrar::EnsureOrg<Runtime>;
+    type Products = ProductRegistry;
+    type Organizations = ConsortiumOrganizations;
+    type IdentifierMaxLength = IdentifierMaxLength;
+    type ShipmentMaxProducts = ShipmentMaxProducts;
+    type HandoverTimeout = HandoverTimeout;
+    type EnforceProductOwnership = EnforceProductOwnership;
+    type ItineraryMaxLegs = ItineraryMaxLegs;
+    type DeviationRadius = DeviationRadius;
+    type DeviationTimeTolerance = DeviationTimeTolerance;
+    type DeviceMaxSubmissions = DeviceMaxSubmissions;
+    type MaxReadingsPerSubmission = MaxReadingsPerSubmission;
+    type UnsignedPriority = TrackingUnsignedPriority;
+    type UnsignedLongevity = TrackingUnsignedLongevity;
+    type NotificationRetention = NotificationRetention;
+    type NotificationMaxPrune = NotificationMaxPrune;
+    type OcwMaxBacktrackPeriod = OcwMaxBacktrackPeriod;
+    type MaxShipmentsPerRecall = MaxShipmentsPerRecall;
 }
 
 impl rbac::Trait for Runtime {
@@ -494,8 +592,8 @@ construct_runtime!(
         PalletDid: pallet_did::{Module, Call, Storage, Event<T>},
         TransactionPayment: pallet_transaction_payment::{Module, Storage},
         Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
-        ProductRegistry: product_registry::{Module, Call, Storage, Event<T>},
-        ProductTracking: product_tracking::{Module, Call, Storage, Event<T>},
+        ProductRegistry: product_registry::{Module, Call, Storage, Event<T>, Config},
+        ProductTracking: product_tracking::{Module, Call, Storage, Event<T>, Config, ValidateUnsigned},
         Registrar: registrar::{Module, Call, Storage, Event<T>, Config<T>},
         Rbac: rbac::{Module, Call, Storage, Event<T>, Config<T>},
     }
@@ -658,4 +756,31 @@ impl_runtime_apis! {
             TransactionPayment::query_info(uxt, len)
         }
     }
+
+    impl product_registry_rpc_runtime_api::ProductRegistryApi<Block, AccountId, Moment> for Runtime {
+        fn product_by_id(id: product_registry::ProductId) -> Option<product_registry::Product<AccountId, Moment>> {
+            ProductRegistry::product_by_id(id)
+        }
+
+        fn products_of_organization(
+            org: AccountId,
+            cursor: Option<product_registry::ProductId>,
+            limit: u32,
+        ) -> product_registry_rpc_runtime_api::ProductsPage<AccountId, Moment> {
+            let (products, next_cursor) = ProductRegistry::products_of_organization_page(&org, cursor, limit);
+            product_registry_rpc_runtime_api::ProductsPage { products, next_cursor }
+        }
+
+        fn valid_certificates(product_id: product_registry::ProductId) -> Vec<product_registry::Certificate<AccountId, Moment>> {
+            ProductRegistry::valid_certificates(&product_id)
+        }
+
+        fn components_tree(product_id: product_registry::ProductId, max_depth: u32) -> Vec<product_registry::ProductId> {
+            ProductRegistry::components_tree(&product_id, max_depth)
+        }
+
+        fn products_containing(product_id: product_registry::ProductId, max_depth: u32) -> Vec<product_registry::ProductId> {
+            ProductRegistry::products_containing(&product_id, max_depth)
+        }
+    }
 }
@@ -18,7 +18,7 @@ app.use(function(req, res, next) {
 });
 
 app.post('/', (req, res) => {
-  console.log('req body:', req.body);
+  console.log('req body:', util.inspect(req.body, { depth: null }));
   res.json({
     status: "acknowledged"
   });
@@ -18,7 +18,23 @@ export default {
     identity: "AccountId",
   },
   PropName: "Vec<u8>",
-  PropValue: "Vec<u8>",
+  PropDecimal: "i64",
+  UnitCode: "Vec<u8>",
+  UnitQuantity: {
+    value: "PropDecimal",
+    unit: "UnitCode",
+  },
+  PropValue: {
+    _enum: {
+      Text: "Vec<u8>",
+      Integer: "i64",
+      Decimal: "PropDecimal",
+      Boolean: "bool",
+      Timestamp: "u64",
+      Hash: "[u8; 32]",
+      UnitQuantity: "UnitQuantity",
+    },
+  },
   ProductProperty: {
     name: "PropName",
     value: "PropValue",
@@ -29,25 +45,114 @@ export default {
     owner: "AccountId",
     props: "Option<Vec<ProductProperty>>",
     registered: "Moment",
+    status: "ProductStatus",
+  },
+  LotId: "Vec<u8>",
+  Lot: {
+    id: "LotId",
+    product_id: "ProductId",
+    produced: "Moment",
+    expires: "Option<Moment>",
+    quantity: "u64",
+    props: "Option<Vec<ProductProperty>>",
+    registered: "Moment",
+  },
+  SchemaId: "Vec<u8>",
+  PropType: {
+    _enum: ["Text", "Integer", "Decimal", "Boolean", "Timestamp", "Hash", "UnitQuantity"],
+  },
+  PropSchema: {
+    name: "PropName",
+    prop_type: "PropType",
+    required: "bool",
+    max_length: "Option<u32>",
+  },
+  ProductRegistration: "(ProductId, AccountId, Option<Vec<ProductProperty>>, Option<SchemaId>)",
+  CertificateId: "Vec<u8>",
+  CertificationScheme: "Vec<u8>",
+  Certificate: {
+    id: "CertificateId",
+    product_id: "ProductId",
+    certifier: "AccountId",
+    scheme: "CertificationScheme",
+    valid_from: "Moment",
+    valid_until: "Moment",
+    doc_hash: "[u8; 32]",
+    issued: "Moment",
+    revoked: "Option<Moment>",
+  },
+  BomComponent: {
+    product_id: "ProductId",
+    quantity: "u32",
+  },
+  ProductStatus: {
+    _enum: ["Active", "Discontinued", "Recalled"],
+  },
+  ProductPropsUpdate: {
+    _enum: {
+      Replace: "Option<Vec<ProductProperty>>",
+      Patch: "Vec<ProductProperty>",
+    },
+  },
+  ProductRevision: {
+    props: "Option<Vec<ProductProperty>>",
+    changed_by: "AccountId",
+    block: "BlockNumber",
+    timestamp: "Moment",
   },
   Identifier: "Vec<u8>",
   Decimal: "i32",
   ShipmentId: "Identifier",
   ShippingEventIndex: "u128",
   DeviceId: "Identifier",
+  DeviceType: "Identifier",
   ShipmentStatus: {
-    _enum: ["Pending", "InTransit", "Delivered"],
+    _enum: [
+      "Pending",
+      "InTransit",
+      "Delivered",
+      "Cancelled",
+      "Returned",
+      "Lost",
+      "Damaged",
+      "Rejected",
+    ],
   },
   Shipment: {
     id: "ShipmentId",
     owner: "AccountId",
     status: "ShipmentStatus",
-    products: "Vec<ProductId>",
+    products: "Vec<ShippedProduct>",
     registered: "Moment",
     delivered: "Option<Moment>",
+    itinerary: "Vec<Leg>",
+  },
+  Leg: {
+    origin: "ReadPoint",
+    destination: "ReadPoint",
+    departure: "Moment",
+    arrival: "Moment",
+    carrier: "AccountId",
+    completed: "Option<Moment>",
+  },
+  Deviation: {
+    _enum: ["OffRoute", "OutsideTimeWindow"],
+  },
+  ShippedProduct: {
+    product_id: "ProductId",
+    lot_id: "Option<LotId>",
   },
   ShippingOperation: {
-    _enum: ["Pickup", "Scan", "Deliver"],
+    _enum: [
+      "Pickup",
+      "Scan",
+      "Deliver",
+      "Cancel",
+      "Return",
+      "ReportLoss",
+      "ReportDamage",
+      "Reject",
+    ],
   },
   ShippingEventType: {
     _enum: [
@@ -55,8 +160,22 @@ export default {
       "ShipmentPickup",
       "ShipmentScan",
       "ShipmentDeliver",
+      "ShipmentRecall",
+      "ShipmentCancel",
+      "ShipmentReturn",
+      "ShipmentLoss",
+      "ShipmentDamage",
+      "ShipmentReject",
+      "ShipmentHandoverPropose",
+      "ShipmentHandoverAccept",
+      "ShipmentHandoverDecline",
     ],
   },
+  CustodyHandover: {
+    from: "AccountId",
+    to: "AccountId",
+    expires: "BlockNumber",
+  },
   ShippingEvent: {
     event_type: "ShippingEventType",
     shipment_id: "ShipmentId",
@@ -78,12 +197,41 @@ export default {
       "Vibration",
     ],
   },
+  ConditionThreshold: {
+    reading_type: "ReadingType",
+    min: "Option<Decimal>",
+    max: "Option<Decimal>",
+    max_excursion: "Option<Moment>",
+  },
+  Excursion: {
+    last_reading: "Moment",
+    out_of_range: "bool",
+    duration: "Moment",
+  },
   Reading: {
     device_id: "DeviceId",
     reading_type: "ReadingType",
     timestamp: "Moment",
     value: "Decimal",
   },
+  SignedReading: {
+    reading: "Reading",
+    signature: "MultiSignature",
+  },
+  ReadingsPayload: {
+    device_id: "DeviceId",
+    nonce: "u64",
+    shipment_id: "ShipmentId",
+    readings: "Vec<Reading>",
+  },
+  Device: {
+    id: "DeviceId",
+    owner: "AccountId",
+    key: "MultiSigner",
+    device_type: "DeviceType",
+    reading_types: "Vec<ReadingType>",
+    active: "bool",
+  },
   Permission: {
     _enum: ["Execute", "Manage"],
   },
@@ -1,25 +1,34 @@
 import React, { useEffect, useState } from 'react';
-import { Table, Message } from 'semantic-ui-react';
+import { Table, Message, Button } from 'semantic-ui-react';
 import { u8aToString } from '@polkadot/util';
 
 import { useSubstrate } from '../substrate-lib';
 
+const PAGE_SIZE = 20;
+
 export default function Main (props) {
   const { organization } = props;
   const { api } = useSubstrate();
   const [products, setProducts] = useState([]);
+  // Cursors of the previous pages, and of the current & next page
+  const [cursors, setCursors] = useState([]);
+  const [cursor, setCursor] = useState(null);
+  const [nextCursor, setNextCursor] = useState(null);
+
+  useEffect(() => {
+    setCursors([]);
+    setCursor(null);
+  }, [organization]);
 
   useEffect(() => {
     let unsub = null;
 
     const getProducts = async () => {
-      unsub = await api.query.productRegistry.productsOfOrganization(organization, productIds => {
-        api.query.productRegistry.products.multi(productIds, products => {
-          const validProducts = products
-            .filter(product => !product.isNone)
-            .map(product => product.unwrap());
-          setProducts(validProducts);
-        });
+      // Refresh the current page on every new block
+      unsub = await api.rpc.chain.subscribeNewHeads(async () => {
+        const page = await api.rpc.productRegistry.getProductsOfOrganization(organization, cursor, PAGE_SIZE);
+        setProducts(page.products);
+        setNextCursor(page.next_cursor.isSome ? page.next_cursor.unwrap() : null);
       });
     };
 
@@ -28,7 +37,17 @@ export default function Main (props) {
     }
 
     return () => unsub && unsub();
-  }, [organization, api, setProducts]);
+  }, [organization, cursor, api, setProducts]);
+
+  const previousPage = () => {
+    setCursor(cursors[cursors.length - 1]);
+    setCursors(cursors.slice(0, -1));
+  };
+
+  const nextPage = () => {
+    setCursors([...cursors, cursor]);
+    setCursor(nextCursor);
+  };
 
   if (!products || products.length === 0) {
     return <Message warning>
@@ -52,8 +71,17 @@ export default function Main (props) {
       return <Table.Row key={id}>
         <Table.Cell>{ id }</Table.Cell>
         <Table.Cell>{ product.owner.toString() }</Table.Cell>
-        <Table.Cell>{ u8aToString(props[0].value) }</Table.Cell>
+        <Table.Cell>{ props[0].value.isText ? u8aToString(props[0].value.asText) : props[0].value.toString() }</Table.Cell>
       </Table.Row>;
     })}</Table.Body>
+
+    <Table.Footer>
+      <Table.Row>
+        <Table.HeaderCell colSpan='3'>
+          <Button size='small' disabled={cursors.length === 0} onClick={previousPage}>Previous</Button>
+          <Button size='small' disabled={!nextCursor} onClick={nextPage}>Next</Button>
+        </Table.HeaderCell>
+      </Table.Row>
+    </Table.Footer>
   </Table>;
 }
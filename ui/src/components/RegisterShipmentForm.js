@@ -123,7 +123,9 @@ function RegisterShipmentFormComponent (props) {
               attrs={{
                 palletRpc: 'productTracking',
                 callable: 'registerShipment',
-                inputParams: [state.shipmentId, state.owner, [state.productId1 || '', state.productId2 || ''].join(',')],
+                inputParams: [state.shipmentId, state.owner, [state.productId1, state.productId2]
+                  .filter(productId => productId)
+                  .map(productId => ({ product_id: productId, lot_id: null })), [], []],
                 paramFields: paramFields
               }}
             />
@@ -1,16 +1,33 @@
 import React, { useEffect, useState } from 'react';
 import { Container, Header, Icon, Grid, List, Step, Segment } from 'semantic-ui-react';
 import { useSubstrate } from '../substrate-lib';
-import { hexToString, u8aToString } from '@polkadot/util';
+import { u8aToString } from '@polkadot/util';
 
 import ShipmentOperations from './ShipmentOperations';
 
+const eventIcons = {
+  ShipmentRegistration: 'tasks',
+  ShipmentPickup: 'truck',
+  ShipmentScan: 'barcode',
+  ShipmentDeliver: 'home',
+  ShipmentRecall: 'undo',
+  ShipmentCancel: 'cancel',
+  ShipmentReturn: 'reply',
+  ShipmentLoss: 'question',
+  ShipmentDamage: 'warning sign',
+  ShipmentReject: 'hand paper',
+  ShipmentHandoverPropose: 'exchange',
+  ShipmentHandoverAccept: 'handshake',
+  ShipmentHandoverDecline: 'ban'
+};
+
 function ShipmentDetailsComponent (props) {
   const { api } = useSubstrate();
   const [shipment, setShipment] = useState(null);
   const [eventIndices, setEventIndices] = useState([]);
   const [events, setEvents] = useState([]);
   const [products, setProducts] = useState([]);
+  const [compromised, setCompromised] = useState(false);
   const { accountPair, shipmentId } = props;
 
   useEffect(() => {
@@ -38,6 +55,23 @@ function ShipmentDetailsComponent (props) {
     }
   }, [api.query.palletDid, api.query.productTracking, api.registry, shipmentId]);
 
+  useEffect(() => {
+    let unsubscribe;
+
+    async function compromised (shipmentId) {
+      unsubscribe = await api.query.productTracking.compromised(shipmentId, data =>
+        setCompromised(data.isTrue)
+      );
+    }
+
+    if (shipmentId) {
+      compromised(shipmentId);
+    } else {
+      setCompromised(false);
+    }
+    return () => unsubscribe && unsubscribe();
+  }, [api.query.productTracking, shipmentId]);
+
   useEffect(() => {
     let unsubscribe;
 
@@ -88,16 +122,16 @@ function ShipmentDetailsComponent (props) {
 
     async function products (shipment) {
       const futures = shipment.products
-        .map(productId => api.query.productRegistry.products(productId.toString()));
+        .map(p => api.rpc.productRegistry.getDecodedProduct(p.product_id));
       Promise.all(futures)
         .then(data => {
           if (data) {
-            const products = data.map(p => {
-              const product = p.value;
-              const descProp = product.props ? product.props.value.find(prop => hexToString(prop.name.toString()) === 'desc') : { name: '', value: '' };
+            const products = data.filter(p => p.isSome).map(p => {
+              const product = p.unwrap();
+              const descProp = product.props.isSome ? product.props.unwrap().find(prop => prop.name.toString() === 'desc') : null;
               return {
-                id: hexToString(product.id.toString()),
-                desc: hexToString(descProp.value.toString())
+                id: product.id.toString(),
+                desc: descProp ? descProp.value.toString() : ''
               };
             });
             setProducts(products);
@@ -114,7 +148,7 @@ function ShipmentDetailsComponent (props) {
       setProducts([]);
       return () => unsubscribe && unsubscribe();
     }
-  }, [api.query.productRegistry, shipment]);
+  }, [api.rpc.productRegistry, shipment]);
 
   return (
     shipment != null
@@ -136,6 +170,9 @@ function ShipmentDetailsComponent (props) {
               <Grid.Column>
                 <Header as="h4" floated="left">Status: </Header>
                 <span>{shipment.status.toString()}</span>
+                { compromised && <span style={{ color: 'red', marginLeft: '0.5em' }}>
+                  <Icon name='warning sign' />Compromised
+                </span> }
               </Grid.Column>
               <Grid.Column>
                 <Header as="h4" floated="left">Delivered:</Header>
@@ -154,13 +191,7 @@ function ShipmentDetailsComponent (props) {
                 const eventType = event.event_type.toString();
                 return (
                   <Step key={idx}>
-                    <Icon name={ eventType === 'ShipmentRegistration'
-                      ? 'tasks'
-                      : eventType === 'ShipmentPickup'
-                        ? 'truck'
-                        : eventType === 'ShipmentScan'
-                          ? 'barcode' : 'home'
-                    } />
+                    <Icon name={ eventIcons[eventType] || 'home' } />
                     <Step.Content>
                       <Step.Title>{event.event_type.toString().substring(8)}</Step.Title>
                       <Step.Description>
@@ -51,7 +51,7 @@ export default function Main (props) {
 
     <Table.Body>{ shipments.map(shipment => {
       const id = u8aToString(shipment.id);
-      const products = shipment.products.map(p => u8aToString(p));
+      const products = shipment.products.map(p => u8aToString(p.product_id));
       return <Table.Row key={id}>
         <Table.Cell>{ id }</Table.Cell>
         <Table.Cell>{ shipment.owner.toString() }</Table.Cell>
@@ -12,7 +12,9 @@ export default function Main (props) {
     longitude: 0.0,
     deviceId: '',
     sensorType: '',
-    sensorValue: 0.0
+    sensorValue: 0.0,
+    readingTimestamp: '',
+    signature: ''
   });
   const sensorTypes = [
     'Humidity', 'Pressure', 'Shock', 'Tilt',
@@ -23,21 +25,29 @@ export default function Main (props) {
 
   if (!shipment) return null;
 
-  return <Fragment>
+  const isMoving = shipment.status.isInTransit || shipment.status.isDamaged || shipment.status.isRejected;
+  const isFinal = shipment.status.isDelivered || shipment.status.isCancelled ||
+    shipment.status.isReturned || shipment.status.isLost;
+
+  const operationButton = (label, operation, visible) =>
     <TxButton
       accountPair={accountPair}
-      label='Pickup'
+      label={label}
       type='SIGNED-TX'
       setStatus={setStatus}
-      style={{ display: shipment.status.isPending ? 'inline-block' : 'none' }}
+      style={{ display: visible ? 'inline-block' : 'none' }}
       attrs={{
         palletRpc: 'productTracking',
         callable: 'trackShipment',
-        inputParams: [hexToString(shipment.id.toString()), 'Pickup', Date.now().toString(), null, null],
+        inputParams: [hexToString(shipment.id.toString()), operation, Date.now().toString(), null, null],
         paramFields: [{ optional: false }, { optional: false }, { optional: false }, { optional: true }, { optional: true }]
       }}
-    />
-    <Form style={{ display: shipment.status.isInTransit ? 'inline-block' : 'none' }}>
+    />;
+
+  return <Fragment>
+    {operationButton('Pickup', 'Pickup', shipment.status.isPending)}
+    {operationButton('Cancel', 'Cancel', shipment.status.isPending)}
+    <Form style={{ display: isMoving ? 'inline-block' : 'none' }}>
       <Form.Input
         name='latitude'
         label='Latitude'
@@ -77,6 +87,20 @@ export default function Main (props) {
         value={state.sensorValue}
         onChange={handleChange}
       />
+      <Form.Input
+        name='readingTimestamp'
+        label='Reading timestamp'
+        state='readingTimestamp'
+        value={state.readingTimestamp}
+        onChange={handleChange}
+      />
+      <Form.Input
+        name='signature'
+        label='Device signature (sr25519)'
+        state='signature'
+        value={state.signature}
+        onChange={handleChange}
+      />
       <Form.Field>
         <TxButton
           accountPair={accountPair}
@@ -88,30 +112,26 @@ export default function Main (props) {
             callable: 'trackShipment',
             inputParams: [shipment.id, 'Scan', Date.now(),
               (state.latitude !== 0.0 && state.longitude !== 0.0 ? { latitude: state.latitude, longitude: state.longitude } : null),
-              (state.deviceId !== '' && state.sensorType !== '' && state.sensorValue !== 0.0
-                ? [{ deviceId: state.deviceId, readingType: state.sensorType, timestamp: Date.now(), value: state.sensorValue }]
+              (state.deviceId !== '' && state.sensorType !== '' && state.sensorValue !== 0.0 && state.signature !== ''
+                ? [{
+                    reading: { deviceId: state.deviceId, readingType: state.sensorType, timestamp: state.readingTimestamp, value: state.sensorValue },
+                    signature: { Sr25519: state.signature }
+                  }]
                 : null)
             ],
             paramFields: [{ optional: false }, { optional: false }, { optional: false }, { optional: true }, { optional: true }]
           }}
         />
-        <TxButton
-          accountPair={accountPair}
-          label='Deliver'
-          type='SIGNED-TX'
-          setStatus={setStatus}
-          attrs={{
-            palletRpc: 'productTracking',
-            callable: 'trackShipment',
-            inputParams: [shipment.id, 'Deliver', Date.now(), null, null],
-            paramFields: [{ optional: false }, { optional: false }, { optional: false }, { optional: true }, { optional: true }]
-          }}
-        />
+        {operationButton('Deliver', 'Deliver', !shipment.status.isRejected)}
+        {operationButton('Reject', 'Reject', !shipment.status.isRejected)}
+        {operationButton('Return', 'Return', shipment.status.isRejected)}
+        {operationButton('Report damage', 'ReportDamage', shipment.status.isInTransit)}
+        {operationButton('Report loss', 'ReportLoss', true)}
       </Form.Field>
       <div style={{ overflowWrap: 'break-word' }}>{status}</div>
     </Form>
-    <div style={{ display: shipment.status.isDelivered ? 'inline-block' : 'none' }}>
-      No operation available: Shipment has been delivered.
+    <div style={{ display: isFinal ? 'inline-block' : 'none' }}>
+      No operation available: Shipment is {shipment.status.toString()}.
     </div>
   </Fragment>;
 }
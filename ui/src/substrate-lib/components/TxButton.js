@@ -162,6 +162,9 @@ function TxButton ({
 
       // Deal with a vector
       if (type.indexOf('Vec<') >= 0) {
+        // Already an array of values, e.g. structs
+        if (Array.isArray(converted)) return [...memo, converted];
+
         converted = converted.split(',').map(e => e.trim());
         converted = converted.map(single => isNumType(type)
           ? (single.indexOf('.') >= 0 ? Number.parseFloat(single) : Number.parseInt(single))